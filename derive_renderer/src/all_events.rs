@@ -3,25 +3,27 @@ use crate::{event::get_enum_data_types, AllEventsOptions};
 use proc_macro2::TokenStream;
 use quote::quote;
 
-pub(crate) fn generate_all_events_fn(options: AllEventsOptions) -> TokenStream {
+pub(crate) fn generate_all_events_fn(options: AllEventsOptions) -> darling::Result<TokenStream> {
     let ident = options.ident;
     let generics = options.generics;
     let vis = options.vis;
     let data = match options.data {
-        darling::ast::Data::Enum(v) => get_enum_data_types(v)
+        darling::ast::Data::Enum(v) => get_enum_data_types(&v)?
+            .into_iter()
             .map(|v| quote! { #v::EVENT_NAME })
             .collect::<Vec<_>>(),
-        _ => panic!("AllEvents only support enum"),
+        // rejected by `supports(enum_any)`
+        darling::ast::Data::Struct(_) => unreachable!(),
     };
 
-    quote! {
+    Ok(quote! {
         impl #generics #ident #generics {
             #vis fn all_events() -> &'static [&'static str] {
               &[#(#data),*]
             }
 
         }
-    }
+    })
 }
 
 #[cfg(test)]
@@ -33,18 +35,21 @@ mod tests {
     use serde::Serialize;
     use syn::parse_quote;
 
+    #[allow(dead_code)]
     #[derive(Debug, Serialize, Event)]
     #[event(name = "foo", receivers = "#foo", target = "#foo")]
     struct Foo1<'a> {
         bar: &'a str,
     }
 
+    #[allow(dead_code)]
     #[derive(Debug, Serialize, Event)]
     #[event(name = "bar", receivers = "#bar", target = "#bar")]
     struct Bar1<'a> {
         bar: &'a str,
     }
 
+    #[allow(dead_code)]
     #[derive(Debug, Serialize, Event)]
     #[event(name = "baz", receivers = "#baz", target = "dynamic")]
     struct Baz1<'a> {
@@ -72,7 +77,32 @@ mod tests {
                 }
             }
         };
-        let actual = generate_all_events_fn(options).to_string();
+        let actual = generate_all_events_fn(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
+
+    #[test]
+    fn all_events_should_only_support_enum() {
+        let input = parse_quote! {
+            #[derive(Debug, AllEvents)]
+            struct AllEvents<'a> {
+                foo: Foo1<'a>,
+            }
+        };
+        let err = AllEventsOptions::from_derive_input(&input).unwrap_err();
+        assert!(err.to_string().starts_with("Unsupported shape `struct`"));
+    }
+
+    #[test]
+    fn all_events_with_invalid_type_should_fail() {
+        let input = parse_quote! {
+            #[derive(Debug, AllEvents)]
+            enum AllEvents<'a> {
+                Foo2(&'a Foo1<'a>),
+            }
+        };
+        let options = AllEventsOptions::from_derive_input(&input).unwrap();
+        let err = generate_all_events_fn(options).unwrap_err();
+        assert_eq!(err.to_string(), "only support standard type path");
+    }
 }
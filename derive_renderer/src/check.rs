@@ -0,0 +1,300 @@
+use crate::TemplateField;
+use darling::{ast::Data, util::Ignored};
+use minijinja::Environment;
+use proc_macro2::Span;
+use syn::{meta::ParseNestedMeta, Attribute, LitStr, Token};
+
+/// check that every variable the template reads is either a field of the context or provided
+/// by the environment. Fields are named the way serde serializes them.
+pub(crate) fn check_template_vars(
+    span: Span,
+    name: &str,
+    source: &str,
+    fields: &[String],
+    globals: &str,
+) -> syn::Result<()> {
+    let env = Environment::new();
+    let tpl = env
+        .template_from_named_str(name, source)
+        .map_err(|e| syn::Error::new(span, format!("failed to parse template `{name}`: {e}")))?;
+    let state = env.empty_state();
+    let globals = globals.split_whitespace().collect::<Vec<_>>();
+
+    let mut unknown = tpl
+        .undeclared_variables(false)
+        .into_iter()
+        .filter(|var| {
+            !fields.contains(var) && !globals.contains(&var.as_str()) && state.lookup(var).is_none()
+        })
+        .collect::<Vec<_>>();
+    unknown.sort();
+
+    let mut errors = unknown.into_iter().map(|var| {
+        syn::Error::new(
+            span,
+            format!(
+                "template `{name}` uses undefined variable `{var}`, available fields: {}",
+                fields.join(", ")
+            ),
+        )
+    });
+    match errors.next() {
+        Some(mut err) => {
+            errors.for_each(|e| err.combine(e));
+            Err(err)
+        }
+        None => Ok(()),
+    }
+}
+
+/// names of the fields as serde serializes them. `None` if they can't be known statically,
+/// e.g. for enums, tuple structs or flattened fields.
+pub(crate) fn context_fields(
+    data: &Data<Ignored, TemplateField>,
+    attrs: &[Attribute],
+) -> Option<Vec<String>> {
+    let Data::Struct(fields) = data else {
+        return None;
+    };
+    let rename_all = serde_container_rename(attrs)?;
+
+    let mut names = Vec::new();
+    for field in fields.iter() {
+        let ident = field.ident.as_ref()?;
+        let serde = serde_field(&field.attrs)?;
+        if serde.flatten {
+            return None;
+        }
+        if serde.skip {
+            continue;
+        }
+        let name = match serde.rename {
+            Some(name) => name,
+            None => {
+                let ident = ident.to_string();
+                let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+                apply_rename_all(rename_all.as_deref(), ident)?
+            }
+        };
+        names.push(name);
+    }
+    Some(names)
+}
+
+#[derive(Default)]
+struct SerdeField {
+    rename: Option<String>,
+    skip: bool,
+    flatten: bool,
+}
+
+/// `rename_all` of the container. Outer `None` if the attributes can't be understood.
+fn serde_container_rename(attrs: &[Attribute]) -> Option<Option<String>> {
+    let mut rename_all = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                rename_all = parse_serialize_name(&meta)?;
+                Ok(())
+            } else {
+                skip_meta(meta)
+            }
+        })
+        .ok()?;
+    }
+    Some(rename_all)
+}
+
+fn serde_field(attrs: &[Attribute]) -> Option<SerdeField> {
+    let mut field = SerdeField::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                field.rename = parse_serialize_name(&meta)?;
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                field.skip = true;
+            } else if meta.path.is_ident("flatten") {
+                field.flatten = true;
+            } else {
+                return skip_meta(meta);
+            }
+            Ok(())
+        })
+        .ok()?;
+    }
+    Some(field)
+}
+
+/// `rename = "a"` or `rename(serialize = "a", deserialize = "b")`
+fn parse_serialize_name(meta: &ParseNestedMeta) -> syn::Result<Option<String>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("serialize") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            skip_meta(meta)
+        }
+    })?;
+    Ok(name)
+}
+
+fn skip_meta(meta: ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(skip_meta)?;
+    }
+    Ok(())
+}
+
+/// the same rules serde applies to snake_case field names
+fn apply_rename_all(rule: Option<&str>, field: &str) -> Option<String> {
+    let pascal = || {
+        let mut ret = String::new();
+        let mut capitalize = true;
+        for c in field.chars() {
+            if c == '_' {
+                capitalize = true;
+            } else if capitalize {
+                ret.push(c.to_ascii_uppercase());
+                capitalize = false;
+            } else {
+                ret.push(c);
+            }
+        }
+        ret
+    };
+
+    let name = match rule {
+        None | Some("lowercase") | Some("snake_case") => field.to_string(),
+        Some("UPPERCASE") | Some("SCREAMING_SNAKE_CASE") => field.to_ascii_uppercase(),
+        Some("PascalCase") => pascal(),
+        Some("camelCase") => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(c) => c.to_ascii_lowercase().to_string() + chars.as_str(),
+                None => pascal,
+            }
+        }
+        Some("kebab-case") => field.replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => field.to_ascii_uppercase().replace('_', "-"),
+        // unknown rule, serde reports it
+        Some(_) => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TemplateOptions;
+    use darling::FromDeriveInput;
+    use syn::parse_quote;
+
+    #[test]
+    fn check_template_vars_should_accept_fields_and_globals() {
+        let fields = vec!["user".to_string(), "items".to_string()];
+        let source = r#"{% set title = "x" %}{{ title }} {{ user.name }} {% for item in items %}{{ loop.index }}{{ item }}{% endfor %}{{ range(3) }}{{ site }}"#;
+        check_template_vars(Span::call_site(), "foo.html.j2", source, &fields, "site").unwrap();
+    }
+
+    #[test]
+    fn check_template_vars_should_reject_unknown_variables() {
+        let fields = vec!["user".to_string()];
+        let err = check_template_vars(
+            Span::call_site(),
+            "foo.html.j2",
+            "{{ usre }} {{ user }} {{ bar }}",
+            &fields,
+            "",
+        )
+        .unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "template `foo.html.j2` uses undefined variable `bar`, available fields: user",
+                "template `foo.html.j2` uses undefined variable `usre`, available fields: user",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_template_vars_should_reject_invalid_syntax() {
+        let err =
+            check_template_vars(Span::call_site(), "foo.html.j2", "{{ user ", &[], "").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("failed to parse template `foo.html.j2`: syntax error"));
+    }
+
+    #[test]
+    fn context_fields_should_follow_serde() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[serde(rename_all = "camelCase", deny_unknown_fields)]
+            #[template(name = "foo.html.j2")]
+            struct Foo {
+                user_name: String,
+                #[serde(rename = "id")]
+                user_id: String,
+                #[serde(rename(serialize = "ts", deserialize = "time"))]
+                created_at: String,
+                #[serde(skip)]
+                secret: String,
+                #[serde(skip_serializing_if = "Option::is_none", default)]
+                r#type: Option<String>,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        let fields = context_fields(&options.data, &options.attrs).unwrap();
+        assert_eq!(fields, ["userName", "id", "ts", "type"]);
+    }
+
+    #[test]
+    fn context_fields_should_give_up_on_flatten_and_enums() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2")]
+            struct Foo {
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        assert!(context_fields(&options.data, &options.attrs).is_none());
+
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2")]
+            enum Foo {
+                A { bar: String },
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        assert!(context_fields(&options.data, &options.attrs).is_none());
+    }
+
+    #[test]
+    fn rename_all_should_match_serde() {
+        let cases = [
+            ("lowercase", "user_name"),
+            ("UPPERCASE", "USER_NAME"),
+            ("PascalCase", "UserName"),
+            ("camelCase", "userName"),
+            ("snake_case", "user_name"),
+            ("SCREAMING_SNAKE_CASE", "USER_NAME"),
+            ("kebab-case", "user-name"),
+            ("SCREAMING-KEBAB-CASE", "USER-NAME"),
+        ];
+        for (rule, expected) in cases {
+            assert_eq!(apply_rename_all(Some(rule), "user_name").unwrap(), expected);
+        }
+        assert!(apply_rename_all(Some("unknown"), "user_name").is_none());
+    }
+}
@@ -1,36 +1,160 @@
-use crate::TemplateOptions;
+use crate::{
+    check::{check_template_vars, context_fields},
+    TemplateOptions,
+};
 // only proc_macro2::TokenStream is testable
 use proc_macro2::TokenStream;
 use quote::quote;
+use std::path::{Path, PathBuf};
 
-pub(crate) fn generate_render_context_trait(options: TemplateOptions) -> TokenStream {
+pub(crate) fn generate_render_context_trait(options: TemplateOptions) -> syn::Result<TokenStream> {
     let TemplateOptions {
         ident,
         generics,
         name,
+        source,
         mime,
-        ..
+        root,
+        partial_block,
+        partial_target,
+        globals,
+        data,
+        attrs,
     } = options;
 
-    let mime_code = if let Some(mime) = mime {
-        quote! { const MIME_TYPE: &'static str = #mime; }
-    } else if name.ends_with("html.j2") {
-        quote! { const MIME_TYPE: &'static str = "text/html; charset=utf-8"; }
-    } else if name.ends_with("json.j2") {
-        quote! { const MIME_TYPE: &'static str = "application/json; charset=utf-8"; }
-    } else {
-        quote! { const MIME_TYPE: &'static str = "text/plain; charset=utf-8"; }
+    let partial_code = match (partial_block, partial_target) {
+        (Some(block), None) => quote! {
+            const PARTIAL_BLOCK: Option<&'static str> = Some(#block);
+        },
+        (Some(block), Some(target)) => {
+            let target = target.as_str();
+            quote! {
+                const PARTIAL_BLOCK: Option<&'static str> = Some(#block);
+                const PARTIAL_TARGET: Option<&'static str> = Some(#target);
+            }
+        }
+        (None, Some(target)) => {
+            return Err(syn::Error::new(
+                target.span(),
+                "`partial_target` requires `partial_block`",
+            ))
+        }
+        (None, None) => quote! {},
+    };
+
+    let fields = context_fields(&data, &attrs);
+    let check_vars = |span, name: &str, source: &str| match &fields {
+        Some(fields) => check_template_vars(span, name, source, fields, &globals),
+        None => Ok(()),
     };
-    quote! {
-        impl #generics jinja_renderer::RenderContext for #ident #generics {
-            const TEMPLATE_NAME: &'static str = #name;
-            #mime_code
 
-            fn render(&self, renderer: &jinja_renderer::Renderer) -> Result<String, jinja_renderer::Error> {
-                renderer.render_template(#name, &self)
+    match (name, source) {
+        (Some(name), None) => {
+            // when the template root is known, make sure the template exists and matches the
+            // context, and rebuild on changes
+            let track_code = match root {
+                Some(root) => {
+                    let path =
+                        template_path(&root, &name).map_err(|e| syn::Error::new(name.span(), e))?;
+                    let source = std::fs::read_to_string(&path).map_err(|e| {
+                        syn::Error::new(name.span(), format!("failed to read {path}: {e}"))
+                    })?;
+                    check_vars(name.span(), &name, &source)?;
+                    quote! { const _: &str = include_str!(#path); }
+                }
+                None => quote! {},
+            };
+
+            let name = name.as_str();
+            let mime = mime.unwrap_or_else(|| mime_from_name(name).to_string());
+            Ok(quote! {
+                impl #generics jinja_renderer::RenderContext for #ident #generics {
+                    const TEMPLATE_NAME: &'static str = #name;
+                    const MIME_TYPE: &'static str = #mime;
+                    #partial_code
+
+                    fn render(&self, renderer: &jinja_renderer::Renderer) -> Result<String, jinja_renderer::Error> {
+                        renderer.render_template(#name, &self)
+                    }
+                }
+
+                #track_code
+            })
+        }
+        (None, Some(source)) => {
+            if root.is_some() {
+                return Err(syn::Error::new(
+                    source.span(),
+                    "`root` can't be used with an inline `source`",
+                ));
             }
+            check_vars(source.span(), &format!("{ident} (inline)"), &source)?;
+
+            // the extension keeps mime detection and auto escaping of the renderer working
+            let mime = mime.unwrap_or_else(|| HTML_MIME.to_string());
+            let name = format!("::{ident}.{}.j2", extension_from_mime(&mime));
+            let source = source.as_str();
+            Ok(quote! {
+                impl #generics jinja_renderer::RenderContext for #ident #generics {
+                    const TEMPLATE_NAME: &'static str = concat!(module_path!(), #name);
+                    const TEMPLATE_SOURCE: Option<&'static str> = Some(#source);
+                    const MIME_TYPE: &'static str = #mime;
+                    #partial_code
+
+                    fn render(&self, renderer: &jinja_renderer::Renderer) -> Result<String, jinja_renderer::Error> {
+                        renderer.render_template(Self::TEMPLATE_NAME, &self)
+                    }
+                }
+            })
         }
+        (Some(name), Some(_)) => Err(syn::Error::new(
+            name.span(),
+            "`name` and `source` can't be used together",
+        )),
+        (None, None) => Err(syn::Error::new(
+            ident.span(),
+            "either `name` or `source` is required in `#[template(...)]`",
+        )),
+    }
+}
+
+const HTML_MIME: &str = "text/html; charset=utf-8";
+const JSON_MIME: &str = "application/json; charset=utf-8";
+const TEXT_MIME: &str = "text/plain; charset=utf-8";
+
+fn mime_from_name(name: &str) -> &'static str {
+    if name.ends_with("html.j2") {
+        HTML_MIME
+    } else if name.ends_with("json.j2") {
+        JSON_MIME
+    } else {
+        TEXT_MIME
+    }
+}
+
+fn extension_from_mime(mime: &str) -> &'static str {
+    if mime.starts_with("text/html") {
+        "html"
+    } else if mime.starts_with("application/json") {
+        "json"
+    } else {
+        "txt"
+    }
+}
+
+/// absolute path of template `name` under `root` (relative to `CARGO_MANIFEST_DIR`)
+fn template_path(root: &str, name: &str) -> Result<String, String> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+    let path: PathBuf = Path::new(&manifest_dir).join(root).join(name);
+    if !path.is_file() {
+        return Err(format!(
+            "template `{name}` not found in `{root}` ({})",
+            path.display()
+        ));
     }
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("{} is not a valid utf-8 path", path.display()))
 }
 
 #[cfg(test)]
@@ -44,7 +168,7 @@ mod tests {
     fn context_should_work() {
         let options = generate_template_input("foo.html.j2", None);
         let expected = generate_template_expected("foo.html.j2", "text/html; charset=utf-8");
-        let actual = generate_render_context_trait(options).to_string();
+        let actual = generate_render_context_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
@@ -52,7 +176,7 @@ mod tests {
     fn context_default_mime_should_work() {
         let options = generate_template_input("foo.js.j2", None);
         let expected = generate_template_expected("foo.js.j2", "text/plain; charset=utf-8");
-        let actual = generate_render_context_trait(options).to_string();
+        let actual = generate_render_context_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
@@ -60,7 +184,169 @@ mod tests {
     fn context_with_mime_should_work() {
         let options = generate_template_input("foo.json.j2", Some("application/json"));
         let expected = generate_template_expected("foo.json.j2", "application/json");
-        let actual = generate_render_context_trait(options).to_string();
+        let actual = generate_render_context_trait(options).unwrap().to_string();
+        assert_eq!(actual, expected.to_string());
+    }
+
+    #[test]
+    fn context_with_root_should_track_template() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("foo.html.j2"), "{{ bar }}").unwrap();
+        let root = dir.path().to_str().unwrap();
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2", root = #root)]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+
+        let path = dir.path().join("foo.html.j2");
+        let path = path.to_str().unwrap();
+        let expected = generate_template_expected("foo.html.j2", "text/html; charset=utf-8");
+        let expected = quote! {
+            #expected
+            const _: &str = include_str!(#path);
+        };
+        let actual = generate_render_context_trait(options).unwrap().to_string();
+        assert_eq!(actual, expected.to_string());
+    }
+
+    #[test]
+    fn context_with_missing_template_should_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2", root = #root)]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+
+        let err = generate_render_context_trait(options).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with(&format!("template `foo.html.j2` not found in `{root}`")));
+    }
+
+    #[test]
+    fn context_with_unknown_variable_should_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("foo.html.j2"), "{{ usre }}").unwrap();
+        let root = dir.path().to_str().unwrap();
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2", root = #root)]
+            struct Foo<'a> {
+                user: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+
+        let err = generate_render_context_trait(options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template `foo.html.j2` uses undefined variable `usre`, available fields: user"
+        );
+    }
+
+    #[test]
+    fn context_with_inline_source_should_work() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(source = "<li>{{ bar }}</li>")]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        let expected = quote! {
+            impl<'a> jinja_renderer::RenderContext for Foo<'a> {
+                const TEMPLATE_NAME: &'static str = concat!(module_path!(), "::Foo.html.j2");
+                const TEMPLATE_SOURCE: Option<&'static str> = Some("<li>{{ bar }}</li>");
+                const MIME_TYPE: &'static str = "text/html; charset=utf-8";
+                fn render(&self, renderer: &jinja_renderer::Renderer) -> Result<String, jinja_renderer::Error> {
+                    renderer.render_template(Self::TEMPLATE_NAME, &self)
+                }
+            }
+        };
+        let actual = generate_render_context_trait(options).unwrap().to_string();
+        assert_eq!(actual, expected.to_string());
+    }
+
+    #[test]
+    fn context_with_inline_source_should_check_variables() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(source = "{{ baz }}", mime = "text/plain")]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        let err = generate_render_context_trait(options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template `Foo (inline)` uses undefined variable `baz`, available fields: bar"
+        );
+    }
+
+    #[test]
+    fn context_requires_either_name_or_source() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2", source = "{{ bar }}")]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        let err = generate_render_context_trait(options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`name` and `source` can't be used together"
+        );
+
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(mime = "text/plain")]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        let err = generate_render_context_trait(options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "either `name` or `source` is required in `#[template(...)]`"
+        );
+    }
+
+    #[test]
+    fn context_with_partial_block_should_work() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Template)]
+            #[template(name = "foo.html.j2", partial_block = "main", partial_target = "content")]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = TemplateOptions::from_derive_input(&input).unwrap();
+        let expected = quote! {
+            impl<'a> jinja_renderer::RenderContext for Foo<'a> {
+                const TEMPLATE_NAME: &'static str = "foo.html.j2";
+                const MIME_TYPE: &'static str = "text/html; charset=utf-8";
+                const PARTIAL_BLOCK: Option<&'static str> = Some("main");
+                const PARTIAL_TARGET: Option<&'static str> = Some("content");
+                fn render(&self, renderer: &jinja_renderer::Renderer) -> Result<String, jinja_renderer::Error> {
+                    renderer.render_template("foo.html.j2", &self)
+                }
+            }
+        };
+        let actual = generate_render_context_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
@@ -0,0 +1,160 @@
+// only proc_macro2::TokenStream is testable
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::path::{Path, PathBuf};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    LitStr, Token,
+};
+use walkdir::WalkDir;
+
+const DEFAULT_GLOB: &str = "**/*.j2";
+
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// `embed_templates!("templates")` or `embed_templates!("templates", "pages/*.j2")`
+pub(crate) struct EmbedInput {
+    dir: LitStr,
+    pattern: Option<LitStr>,
+}
+
+impl Parse for EmbedInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let args = Punctuated::<LitStr, Token![,]>::parse_terminated(input)?;
+        let mut args = args.into_iter();
+        let Some(dir) = args.next() else {
+            return Err(
+                input.error("expected template directory, e.g. `embed_templates!(\"templates\")`")
+            );
+        };
+        let pattern = args.next();
+        if let Some(extra) = args.next() {
+            return Err(syn::Error::new(
+                extra.span(),
+                "expected at most a directory and a glob",
+            ));
+        }
+        Ok(Self { dir, pattern })
+    }
+}
+
+pub(crate) fn generate_embed_templates(input: EmbedInput) -> syn::Result<TokenStream> {
+    let root = std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .map_err(|_| syn::Error::new(Span::call_site(), "CARGO_MANIFEST_DIR is not set"))?;
+    let pattern = input
+        .pattern
+        .as_ref()
+        .map(|p| p.value())
+        .unwrap_or_else(|| DEFAULT_GLOB.to_string());
+    let span = input.pattern.as_ref().unwrap_or(&input.dir).span();
+    let pattern = glob::Pattern::new(&pattern)
+        .map_err(|e| syn::Error::new(span, format!("invalid glob: {e}")))?;
+
+    let dir = root.join(input.dir.value());
+    let templates =
+        collect_templates(&dir, &pattern).map_err(|e| syn::Error::new(input.dir.span(), e))?;
+
+    Ok(generate_templates_code(&templates))
+}
+
+fn collect_templates(dir: &Path, pattern: &glob::Pattern) -> Result<Vec<(String, String)>, String> {
+    if !dir.is_dir() {
+        return Err(format!("template directory {} not found", dir.display()));
+    }
+
+    let mut templates = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(dir) else {
+            continue;
+        };
+        let parts = relative
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("{} is not a valid utf-8 path", relative.display()))?;
+        let name = parts.join("/");
+        if !pattern.matches_with(&name, MATCH_OPTIONS) {
+            continue;
+        }
+        let path = entry
+            .path()
+            .to_str()
+            .ok_or_else(|| format!("{} is not a valid utf-8 path", entry.path().display()))?
+            .to_string();
+        templates.push((name, path));
+    }
+    Ok(templates)
+}
+
+fn generate_templates_code(templates: &[(String, String)]) -> TokenStream {
+    // include_str! makes cargo rebuild the crate whenever an embedded template changes
+    let items = templates.iter().map(|(name, path)| {
+        quote! { jinja_renderer::OwnedTemplate::new(#name, include_str!(#path)) }
+    });
+    quote! {
+        [#(#items),*].into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn collect_templates_should_use_relative_names() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "index.html.j2");
+        write(dir.path(), "pages/home.html.j2");
+        write(dir.path(), "pages/readme.md");
+
+        let pattern = glob::Pattern::new(DEFAULT_GLOB).unwrap();
+        let names = collect_templates(dir.path(), &pattern)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["index.html.j2", "pages/home.html.j2"]);
+
+        let pattern = glob::Pattern::new("pages/*").unwrap();
+        let names = collect_templates(dir.path(), &pattern)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["pages/home.html.j2", "pages/readme.md"]);
+    }
+
+    #[test]
+    fn collect_templates_should_fail_on_missing_dir() {
+        let pattern = glob::Pattern::new(DEFAULT_GLOB).unwrap();
+        let err = collect_templates(Path::new("/not/exists"), &pattern).unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[test]
+    fn embed_templates_should_work() {
+        let templates = vec![("foo.html.j2".to_string(), "/tpl/foo.html.j2".to_string())];
+        let expected = quote! {
+            [jinja_renderer::OwnedTemplate::new("foo.html.j2", include_str!("/tpl/foo.html.j2"))].into_iter()
+        };
+        let actual = generate_templates_code(&templates).to_string();
+        assert_eq!(actual, expected.to_string());
+    }
+
+    fn write(root: &Path, name: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+}
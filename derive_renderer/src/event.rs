@@ -1,15 +1,21 @@
-use crate::{EnumData, EventOptions, FieldData};
+use crate::{
+    selector::{check_id_prefix, check_id_selector, check_selector, split_receivers},
+    swap::generate_swap,
+    EnumData, EventOptions,
+};
 // only proc_macro2::TokenStream is testable
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::Ident;
+use syn::{spanned::Spanned, Ident};
 
-pub(crate) fn generate_event_trait(options: EventOptions) -> TokenStream {
+pub(crate) fn generate_event_trait(options: EventOptions) -> darling::Result<TokenStream> {
     let EventOptions {
         ident,
         generics,
         name,
         receivers,
+        receivers_field,
+        receivers_prefix,
         target,
         swap,
         id_field,
@@ -18,45 +24,112 @@ pub(crate) fn generate_event_trait(options: EventOptions) -> TokenStream {
         attrs,
     } = options;
 
-    let receivers = receivers
-        .trim()
-        .split(' ')
-        .map(|s| s.trim())
-        .collect::<Vec<_>>();
-
-    if name.is_empty() || receivers.is_empty() || target.is_empty() {
-        match data {
-            darling::ast::Data::Struct(_) => {
-                panic!("event name, receivers and target must not be empty for struct")
-            }
-            darling::ast::Data::Enum(data) => {
-                return generate_event_trait_for_enum(&ident, &generics, data);
-            }
+    let fields = match data {
+        darling::ast::Data::Struct(fields) => fields,
+        darling::ast::Data::Enum(data) => {
+            return generate_event_trait_for_enum(&ident, &generics, data);
         }
+    };
+
+    let mut errors = darling::Error::accumulator();
+    if name.is_empty() {
+        errors.push(error(
+            name_span(&name, &ident),
+            "event name must not be empty",
+        ));
+    }
+    if target.is_empty() {
+        errors.push(error(
+            name_span(&target, &ident),
+            "event target must not be empty",
+        ));
     }
 
-    // TODO: use regex to do more tests
+    let receivers_span = name_span(&receivers, &ident);
+    let receivers = split_receivers(&receivers).collect::<Vec<_>>();
+    if receivers.is_empty() && receivers_field.is_none() {
+        errors.push(error(receivers_span, "event receivers must not be empty"));
+    }
     for receiver in &receivers {
-        if !receiver.starts_with('#') {
-            panic!("receivers must start with #, and separated by space");
+        if let Err(e) = check_id_selector(receiver) {
+            errors.push(error(receivers_span, format!("invalid receiver: {e}")));
         }
     }
 
-    let target_code = if target == "dynamic" {
-        let id_ident = Ident::new(&id_field, Span::call_site());
-        if !check_id_exists(&data, &id_field) {
-            panic!("target is dynamic, but {id_field} field not found");
-        } else {
-            quote! { format!("{}{}", #id_prefix, self.#id_ident).into() }
+    let receivers_code = match &receivers_field {
+        Some(field) => {
+            let field_ident = errors.handle(field_ident(field, "receivers_field"));
+            let exists = fields
+                .iter()
+                .any(|f| f.ident.is_some() && f.ident == field_ident);
+            if field_ident.is_some() && !exists {
+                errors.push(error(
+                    field.span(),
+                    format!("receivers_field `{}` not found", field.as_str()),
+                ));
+            }
+            if let Err(e) = check_id_prefix(&receivers_prefix) {
+                errors.push(error(
+                    receivers_prefix.span(),
+                    format!("invalid receivers_prefix: {e}"),
+                ));
+            }
+            let prefix = receivers_prefix.as_str();
+            let dynamic = quote! {
+                (&self.#field_ident).into_iter().map(|v| format!("{}{}", #prefix, v).into())
+            };
+            if receivers.is_empty() {
+                quote! { #dynamic.collect() }
+            } else {
+                quote! { [#(#receivers),*].into_iter().map(Into::into).chain(#dynamic).collect() }
+            }
         }
+        None => quote! { vec![#(#receivers.into()),*] },
+    };
+
+    let target_code = if target.as_str() == "dynamic" {
+        let id_ident = errors.handle(field_ident(&id_field, "id_field"));
+        let id_exists = fields
+            .iter()
+            .any(|f| f.ident.is_some() && f.ident == id_ident);
+        if id_ident.is_some() && !id_exists {
+            errors.push(error(
+                id_field.span(),
+                format!(
+                    "target is dynamic, but {} field not found",
+                    id_field.as_str()
+                ),
+            ));
+        }
+        if let Err(e) = check_id_prefix(&id_prefix) {
+            errors.push(error(
+                name_span(&id_prefix, &ident),
+                format!("invalid id_prefix: {e}"),
+            ));
+        }
+        let id_prefix = id_prefix.as_str();
+        quote! { format!("{}{}", #id_prefix, self.#id_ident).into() }
     } else {
+        // an empty target is reported above
+        match check_selector(&target) {
+            Err(e) if !target.is_empty() => {
+                errors.push(error(target.span(), format!("invalid target: {e}")));
+            }
+            _ => {}
+        }
+        let target = target.as_str();
         quote! { #target.into() }
     };
+    let swap = errors.handle(generate_swap(&swap)).unwrap_or_default();
+    errors.finish()?;
+
+    let name = name.as_str();
+    let id_field = id_field.as_str();
     let event_info_code = quote! {
       fn event_info(&self) -> jinja_renderer::EventInfo {
         jinja_renderer::EventInfo {
           name: #name,
-          receivers: &[#(#receivers),*],
+          receivers: #receivers_code,
           target: #target_code,
           swap: #swap,
           id_field: #id_field,
@@ -65,13 +138,22 @@ pub(crate) fn generate_event_trait(options: EventOptions) -> TokenStream {
     };
 
     let event_info_with_data = quote! {
-        serde_json::to_string(&serde_json::json!({"info": self.event_info(), "data": self})).expect("even info should be a valid json")
+        jinja_renderer::__private::event_json(&self.event_info(), self)
+    };
+    let render_html_code = if !attrs.is_empty() {
+        quote! {
+            fn render_html(&self, renderer: &jinja_renderer::Renderer) -> Result<Option<String>, jinja_renderer::Error> {
+                Ok(Some(jinja_renderer::RenderContext::render(self, renderer)?))
+            }
+        }
+    } else {
+        quote! {}
     };
     let render_event_code = if !attrs.is_empty() {
         // if template is specified, render template and attach it to event info
         quote! {
-            let mut ret = #event_info_with_data;
-            let data = self.render(renderer)?;
+            let mut ret = #event_info_with_data?;
+            let data = jinja_renderer::RenderContext::render(self, renderer)?;
             ret.push_str("\n");
             ret.push_str(&data);
             Ok(ret)
@@ -79,11 +161,11 @@ pub(crate) fn generate_event_trait(options: EventOptions) -> TokenStream {
     } else {
         // otherwise, put the data into event info
         quote! {
-          Ok(#event_info_with_data)
+          #event_info_with_data
         }
     };
 
-    quote! {
+    Ok(quote! {
         impl #generics jinja_renderer::RenderEvent for #ident #generics {
             const EVENT_NAME: &'static str = #name;
 
@@ -91,45 +173,82 @@ pub(crate) fn generate_event_trait(options: EventOptions) -> TokenStream {
                 #render_event_code
             }
 
+            fn render_event(&self, renderer: &jinja_renderer::Renderer) -> Result<jinja_renderer::RenderedEvent, jinja_renderer::Error> {
+                let html = jinja_renderer::RenderEvent::render_html(self, renderer)?;
+                jinja_renderer::__private::rendered_event(self.event_info(), self, html)
+            }
+
+            #render_html_code
+
             #event_info_code
         }
 
 
-    }
+    })
 }
 
 pub(crate) fn get_enum_idents(data: Vec<EnumData>) -> impl Iterator<Item = Ident> {
     data.into_iter().map(|v| v.ident)
 }
 
-pub(crate) fn get_enum_data_types(data: Vec<EnumData>) -> impl Iterator<Item = TokenStream> {
-    data.into_iter().map(|v| match v.fields.style {
-        darling::ast::Style::Tuple => {
-            if v.fields.fields.len() != 1 {
-                panic!("only support tuple struct with one field");
-            }
-            let ty = &v.fields.fields[0].ty;
-            let names = match ty {
-                syn::Type::Path(p) => {
-                    let path = &p.path;
-                    path.segments.iter().map(|s| &s.ident).collect::<Vec<_>>()
+/// the event type of each variant, every variant must be a tuple with exactly one event type
+pub(crate) fn get_enum_data_types(data: &[EnumData]) -> darling::Result<Vec<TokenStream>> {
+    let mut errors = darling::Error::accumulator();
+    let types = data
+        .iter()
+        .filter_map(|v| {
+            errors.handle_in(|| {
+                if v.fields.style != darling::ast::Style::Tuple || v.fields.fields.len() != 1 {
+                    return Err(error(
+                        v.ident.span(),
+                        "only support tuple variant with one field, e.g. `Foo(Foo)`",
+                    ));
                 }
-                _ => panic!("only support standard type path"),
-            };
-            quote! { #(#names)::* }
-        }
-        _ => panic!("only support tuple struct"),
+                match &v.fields.fields[0].ty {
+                    syn::Type::Path(p) => {
+                        let names = p.path.segments.iter().map(|s| &s.ident);
+                        Ok(quote! { #(#names)::* })
+                    }
+                    ty => Err(error(ty.span(), "only support standard type path")),
+                }
+            })
+        })
+        .collect();
+    errors.finish_with(types)
+}
+
+pub(crate) fn error(span: Span, msg: impl std::fmt::Display) -> darling::Error {
+    syn::Error::new(span, msg).into()
+}
+
+/// the field named by an attribute value, e.g. `id_field = "id"`
+fn field_ident(value: &darling::util::SpannedValue<String>, attr: &str) -> darling::Result<Ident> {
+    syn::parse_str::<Ident>(value).map_err(|_| {
+        error(
+            value.span(),
+            format!("invalid {attr}: `{}` is not a field name", value.as_str()),
+        )
     })
 }
 
+/// span of an attribute value, or of the type if the value is not given
+fn name_span(value: &darling::util::SpannedValue<String>, ident: &Ident) -> Span {
+    if value.is_empty() {
+        ident.span()
+    } else {
+        value.span()
+    }
+}
+
 fn generate_event_trait_for_enum(
     ident: &Ident,
     generics: &syn::Generics,
     data: Vec<EnumData>,
-) -> TokenStream {
+) -> darling::Result<TokenStream> {
+    get_enum_data_types(&data)?;
     let data = &get_enum_idents(data).collect::<Vec<_>>();
 
-    quote! {
+    Ok(quote! {
         impl #generics jinja_renderer::RenderEvent for #ident #generics {
             const EVENT_NAME: &'static str = "";
 
@@ -141,31 +260,31 @@ fn generate_event_trait_for_enum(
                 }
             }
 
-            fn event_info(&self) -> jinja_renderer::EventInfo {
+            fn render_event(&self, renderer: &jinja_renderer::Renderer) -> Result<jinja_renderer::RenderedEvent, jinja_renderer::Error> {
                 match self {
                     #(
-                        Self::#data(v) => v.event_info(),
+                        Self::#data(v) => v.render_event(renderer),
                     )*
                 }
             }
-        }
-    }
-}
 
-fn check_id_exists(data: &darling::ast::Data<EnumData, FieldData>, id_field: &str) -> bool {
-    let fields = match data {
-        darling::ast::Data::Struct(v) => &v.fields,
-        _ => return false,
-    };
+            fn render_html(&self, renderer: &jinja_renderer::Renderer) -> Result<Option<String>, jinja_renderer::Error> {
+                match self {
+                    #(
+                        Self::#data(v) => v.render_html(renderer),
+                    )*
+                }
+            }
 
-    for field in fields {
-        if let Some(ref ident) = field.ident {
-            if ident == id_field {
-                return true;
+            fn event_info(&self) -> jinja_renderer::EventInfo {
+                match self {
+                    #(
+                        Self::#data(v) => v.event_info(),
+                    )*
+                }
             }
         }
-    }
-    false
+    })
 }
 
 #[cfg(test)]
@@ -177,18 +296,21 @@ mod tests {
     use serde::Serialize;
     use syn::parse_quote;
 
+    #[allow(dead_code)]
     #[derive(Debug, Serialize, Event)]
     #[event(name = "foo", receivers = "#foo", target = "#foo")]
     struct Foo1<'a> {
         bar: &'a str,
     }
 
+    #[allow(dead_code)]
     #[derive(Debug, Serialize, Event)]
     #[event(name = "bar", receivers = "#bar", target = "#bar")]
     struct Bar1<'a> {
         bar: &'a str,
     }
 
+    #[allow(dead_code)]
     #[derive(Debug, Serialize, Event)]
     #[event(name = "baz", receivers = "#baz", target = "dynamic")]
     struct Baz1<'a> {
@@ -200,7 +322,7 @@ mod tests {
     fn event_without_template_and_id_should_work() {
         let options = generate_event_input("foo", false, false);
         let expected = generate_event_expected(false, false);
-        let actual = generate_event_trait(options).to_string();
+        let actual = generate_event_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
@@ -208,7 +330,7 @@ mod tests {
     fn event_with_template_and_id_should_work() {
         let options = generate_event_input("foo", true, true);
         let expected = generate_event_expected(true, true);
-        let actual = generate_event_trait(options).to_string();
+        let actual = generate_event_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
@@ -216,7 +338,7 @@ mod tests {
     fn event_with_template_and_without_id_should_work() {
         let options = generate_event_input("foo", true, false);
         let expected = generate_event_expected(true, false);
-        let actual = generate_event_trait(options).to_string();
+        let actual = generate_event_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
@@ -224,10 +346,79 @@ mod tests {
     fn event_without_template_and_with_id_should_work() {
         let options = generate_event_input("foo", false, true);
         let expected = generate_event_expected(false, true);
-        let actual = generate_event_trait(options).to_string();
+        let actual = generate_event_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
+    #[test]
+    fn event_with_receivers_field_should_work() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "message", receivers_field = "room_ids", receivers_prefix = "#room-", target = "#messages")]
+            struct Message {
+                room_ids: Vec<u64>,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let actual = generate_event_trait(options).unwrap().to_string();
+        let expected = quote! {
+            receivers: (&self.room_ids).into_iter().map(|v| format!("{}{}", "#room-", v).into()).collect(),
+        };
+        assert!(actual.contains(&expected.to_string()));
+
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "message", receivers = "#inbox", receivers_field = "room_ids", target = "#messages")]
+            struct Message {
+                room_ids: Vec<u64>,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let actual = generate_event_trait(options).unwrap().to_string();
+        let expected = quote! {
+            receivers: ["#inbox"].into_iter().map(Into::into).chain((&self.room_ids).into_iter().map(|v| format!("{}{}", "#", v).into())).collect(),
+        };
+        assert!(actual.contains(&expected.to_string()));
+    }
+
+    #[test]
+    fn event_with_invalid_receivers_field_should_fail() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "message", receivers_field = "rooms", receivers_prefix = "room-", target = "#messages")]
+            struct Message {
+                room_ids: Vec<u64>,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "receivers_field `rooms` not found",
+                "invalid receivers_prefix: `room-` is not an id selector prefix like `#item-`",
+            ]
+        );
+    }
+
+    #[test]
+    fn event_with_non_identifier_receivers_field_should_fail() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "message", receivers_field = "room-ids", target = "#messages")]
+            struct Message {
+                room_ids: Vec<u64>,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid receivers_field: `room-ids` is not a field name"
+        );
+    }
+
     #[test]
     fn enum_event_should_work() {
         let input = parse_quote! {
@@ -252,6 +443,22 @@ mod tests {
                     }
                 }
 
+                fn render_event(&self, renderer: &jinja_renderer::Renderer) -> Result<jinja_renderer::RenderedEvent, jinja_renderer::Error> {
+                    match self {
+                        Self::Foo2(v) => v.render_event(renderer),
+                        Self::Bar2(v) => v.render_event(renderer),
+                        Self::Baz2(v) => v.render_event(renderer),
+                    }
+                }
+
+                fn render_html(&self, renderer: &jinja_renderer::Renderer) -> Result<Option<String>, jinja_renderer::Error> {
+                    match self {
+                        Self::Foo2(v) => v.render_html(renderer),
+                        Self::Bar2(v) => v.render_html(renderer),
+                        Self::Baz2(v) => v.render_html(renderer),
+                    }
+                }
+
                 fn event_info(&self) -> jinja_renderer::EventInfo {
                     match self {
                         Self::Foo2(v) => v.event_info(),
@@ -261,10 +468,121 @@ mod tests {
                 }
             }
         };
-        let actual = generate_event_trait(options).to_string();
+        let actual = generate_event_trait(options).unwrap().to_string();
         assert_eq!(actual, expected.to_string());
     }
 
+    #[test]
+    fn invalid_event_should_report_all_errors() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(receivers = "bar", target = "dynamic", id_field = "foo_id")]
+            struct Foo<'a> {
+                bar: &'a str,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "event name must not be empty",
+                "invalid receiver: `bar` is not an id selector like `#foo`",
+                "target is dynamic, but foo_id field not found",
+            ]
+        );
+    }
+
+    #[test]
+    fn event_with_invalid_id_field_should_fail() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "foo", receivers = "#foo", target = "dynamic", id_field = "my-id")]
+            struct Foo {
+                id: u64,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid id_field: `my-id` is not a field name"
+        );
+    }
+
+    #[test]
+    fn event_selectors_should_be_validated() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "foo", receivers = "#a,  #b\n#1c", target = "ul >")]
+            struct Foo {}
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "invalid receiver: `#1c` is not an id selector like `#foo`",
+                "invalid target: `ul >` is not a valid selector: expected a selector",
+            ]
+        );
+
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "foo", receivers = "", target = "dynamic", id_prefix = "item-")]
+            struct Foo {
+                id: u64,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "event receivers must not be empty",
+                "invalid id_prefix: `item-` is not an id selector prefix like `#item-`",
+            ]
+        );
+    }
+
+    #[test]
+    fn event_receivers_should_allow_commas_and_whitespace() {
+        let input = parse_quote! {
+            #[derive(Debug, Serialize, Event)]
+            #[event(name = "foo", receivers = " #a  #b,#c ", target = "ul#list > li.item[data-x='1']")]
+            struct Foo {}
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let actual = generate_event_trait(options).unwrap().to_string();
+        let expected = quote! { receivers: vec!["#a".into(), "#b".into(), "#c".into()], };
+        assert!(actual.contains(&expected.to_string()));
+    }
+
+    #[test]
+    fn enum_event_with_invalid_variants_should_fail() {
+        let input = parse_quote! {
+            #[derive(Debug, Event)]
+            enum EnumEvents<'a> {
+                Foo2(Foo1<'a>),
+                Bar2 { bar: Bar1<'a> },
+                Baz2(Baz1<'a>, Baz1<'a>),
+                Qux2,
+            }
+        };
+        let options = EventOptions::from_derive_input(&input).unwrap();
+        let err = generate_event_trait(options).unwrap_err();
+        assert_eq!(err.len(), 3);
+        for e in err {
+            assert_eq!(
+                e.to_string(),
+                "only support tuple variant with one field, e.g. `Foo(Foo)`"
+            );
+        }
+    }
+
     fn generate_event_input(name: &str, with_template: bool, with_id: bool) -> EventOptions {
         let top_line = if with_template {
             quote! {
@@ -311,19 +629,28 @@ mod tests {
         };
 
         let event_info_with_data = quote! {
-            serde_json::to_string(&serde_json::json!({"info": self.event_info(), "data": self})).expect("even info should be a valid json")
+            jinja_renderer::__private::event_json(&self.event_info(), self)
+        };
+        let render_html = if with_template {
+            quote! {
+                fn render_html(&self, renderer: &jinja_renderer::Renderer) -> Result<Option<String>, jinja_renderer::Error> {
+                    Ok(Some(jinja_renderer::RenderContext::render(self, renderer)?))
+                }
+            }
+        } else {
+            quote! {}
         };
         let render_data = if with_template {
             quote! {
-                let mut ret = #event_info_with_data;
-                let data = self.render(renderer)?;
+                let mut ret = #event_info_with_data?;
+                let data = jinja_renderer::RenderContext::render(self, renderer)?;
                 ret.push_str("\n");
                 ret.push_str(&data);
                 Ok(ret)
             }
         } else {
             quote! {
-                Ok(#event_info_with_data)
+                #event_info_with_data
             }
         };
 
@@ -335,12 +662,19 @@ mod tests {
                     #render_data
                 }
 
+                fn render_event(&self, renderer: &jinja_renderer::Renderer) -> Result<jinja_renderer::RenderedEvent, jinja_renderer::Error> {
+                    let html = jinja_renderer::RenderEvent::render_html(self, renderer)?;
+                    jinja_renderer::__private::rendered_event(self.event_info(), self, html)
+                }
+
+                #render_html
+
                 fn event_info(&self) -> jinja_renderer::EventInfo {
                   jinja_renderer::EventInfo {
                     name: "foo",
-                    receivers: &["#bar"],
+                    receivers: vec!["#bar".into()],
                     target: #target_info,
-                    swap: "innerHTML",
+                    swap: jinja_renderer::SwapSpec::new(jinja_renderer::Swap::InnerHtml, ""),
                     id_field: "id",
                   }
 
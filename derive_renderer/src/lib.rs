@@ -1,22 +1,54 @@
 mod all_events;
+mod check;
 mod context;
+mod embed;
 mod event;
+mod selector;
+mod swap;
 
 use all_events::generate_all_events_fn;
 use context::generate_render_context_trait;
-use darling::{FromDeriveInput, FromField, FromVariant};
+use darling::{util::SpannedValue, FromDeriveInput, FromField, FromVariant};
+use embed::{generate_embed_templates, EmbedInput};
 use event::generate_event_trait;
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use syn::{parse_macro_input, DeriveInput, Ident};
 
 #[derive(Debug, FromDeriveInput)]
-#[darling(attributes(template))]
+#[darling(attributes(template), forward_attrs(serde))]
 struct TemplateOptions {
     ident: Ident,
     generics: syn::Generics,
-    name: String,
+    #[darling(default)]
+    name: Option<SpannedValue<String>>,
+    /// inline template source, used instead of `name` and registered with
+    /// `Renderer::add_inline_template`
+    #[darling(default)]
+    source: Option<SpannedValue<String>>,
     #[darling(default)]
     mime: Option<String>,
+    /// template directory relative to `CARGO_MANIFEST_DIR`, enables compile time checks
+    #[darling(default)]
+    root: Option<String>,
+    /// block rendered instead of the full page for htmx requests
+    #[darling(default)]
+    partial_block: Option<String>,
+    /// only render `partial_block` when the htmx request targets this element id
+    #[darling(default)]
+    partial_target: Option<SpannedValue<String>>,
+    /// space separated variables provided by the environment (e.g. `add_global`)
+    #[darling(default)]
+    globals: String,
+    data: darling::ast::Data<darling::util::Ignored, TemplateField>,
+    attrs: Vec<syn::Attribute>,
+}
+
+#[derive(Debug, FromField)]
+#[darling(forward_attrs(serde))]
+struct TemplateField {
+    ident: Option<syn::Ident>,
+    attrs: Vec<syn::Attribute>,
 }
 
 #[derive(Debug, FromDeriveInput)]
@@ -25,22 +57,28 @@ struct EventOptions {
     ident: Ident,
     generics: syn::Generics,
     #[darling(default)]
-    name: String,
+    name: SpannedValue<String>,
+    #[darling(default)]
+    receivers: SpannedValue<String>,
+    /// a collection field whose items address receivers at runtime, as `{receivers_prefix}{item}`
     #[darling(default)]
-    receivers: String,
+    receivers_field: Option<SpannedValue<String>>,
+    #[darling(default = "default_receivers_prefix")]
+    receivers_prefix: SpannedValue<String>,
     #[darling(default = "default_target")]
-    target: String,
+    target: SpannedValue<String>,
     #[darling(default = "default_swap")]
-    swap: String,
+    swap: SpannedValue<String>,
     #[darling(default = "default_id_field")]
-    id_field: String,
+    id_field: SpannedValue<String>,
     #[darling(default = "default_id_prefix")]
-    id_prefix: String,
+    id_prefix: SpannedValue<String>,
     data: darling::ast::Data<EnumData, FieldData>,
     attrs: Vec<syn::Attribute>,
 }
 
 #[derive(Debug, FromDeriveInput)]
+#[darling(supports(enum_any))]
 struct AllEventsOptions {
     ident: Ident,
     generics: syn::Generics,
@@ -63,36 +101,64 @@ struct EnumData {
 #[proc_macro_derive(Template, attributes(template))]
 pub fn derive_template(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let options = TemplateOptions::from_derive_input(&input).expect("failed to parse input");
-    generate_render_context_trait(options).into()
+    TemplateOptions::from_derive_input(&input)
+        .and_then(|options| generate_render_context_trait(options).map_err(Into::into))
+        .unwrap_or_else(darling::Error::write_errors)
+        .into()
 }
 
 #[proc_macro_derive(Event, attributes(event))]
 pub fn derive_event(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let options = EventOptions::from_derive_input(&input).expect("failed to parse input");
-    generate_event_trait(options).into()
+    EventOptions::from_derive_input(&input)
+        .and_then(generate_event_trait)
+        .unwrap_or_else(darling::Error::write_errors)
+        .into()
 }
 
 #[proc_macro_derive(AllEvents)]
 pub fn derive_all_events(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let options = AllEventsOptions::from_derive_input(&input).expect("failed to parse input");
-    generate_all_events_fn(options).into()
+    AllEventsOptions::from_derive_input(&input)
+        .and_then(generate_all_events_fn)
+        .unwrap_or_else(darling::Error::write_errors)
+        .into()
+}
+
+/// Embed every template under a directory (relative to `CARGO_MANIFEST_DIR`) into the binary.
+/// Expands to an iterator of `OwnedTemplate` that can be passed to `Renderer::add_templates`.
+///
+/// ```ignore
+/// renderer.add_templates(embed_templates!("templates"))?;
+/// renderer.add_templates(embed_templates!("templates", "pages/**/*.j2"))?;
+/// ```
+///
+/// Editing an embedded template triggers a rebuild. Adding a new file does not, so add
+/// `println!("cargo:rerun-if-changed=templates")` to your `build.rs` if you need that.
+#[proc_macro]
+pub fn embed_templates(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as EmbedInput);
+    generate_embed_templates(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn default_swap() -> SpannedValue<String> {
+    SpannedValue::new("innerHTML".to_string(), Span::call_site())
 }
 
-fn default_swap() -> String {
-    "innerHTML".to_string()
+fn default_receivers_prefix() -> SpannedValue<String> {
+    SpannedValue::new("#".to_string(), Span::call_site())
 }
 
-fn default_target() -> String {
-    "dynamic".to_string()
+fn default_target() -> SpannedValue<String> {
+    SpannedValue::new("dynamic".to_string(), Span::call_site())
 }
 
-fn default_id_field() -> String {
-    "id".to_string()
+fn default_id_field() -> SpannedValue<String> {
+    SpannedValue::new("id".to_string(), Span::call_site())
 }
 
-fn default_id_prefix() -> String {
-    "#id-".to_string()
+fn default_id_prefix() -> SpannedValue<String> {
+    SpannedValue::new("#id-".to_string(), Span::call_site())
 }
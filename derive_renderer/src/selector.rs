@@ -0,0 +1,281 @@
+//! Validation of the CSS selectors used by events: receivers are id selectors, targets can be any
+//! selector made of type, `*`, id, class and attribute selectors with combinators, or a list of
+//! them. Pseudo classes and htmx extended selectors (`closest`, `find`...) are not supported.
+
+use std::{iter::Peekable, str::CharIndices};
+
+/// receivers are separated by any mix of whitespace and commas
+pub(crate) fn split_receivers(receivers: &str) -> impl Iterator<Item = &str> {
+    receivers
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+}
+
+/// `#foo`
+pub(crate) fn check_id_selector(selector: &str) -> Result<(), String> {
+    match selector.strip_prefix('#') {
+        Some(id) if is_ident(id) => Ok(()),
+        _ => Err(format!("`{selector}` is not an id selector like `#foo`")),
+    }
+}
+
+/// a prefix that forms an id selector once a value is appended, e.g. `#room-`
+pub(crate) fn check_id_prefix(prefix: &str) -> Result<(), String> {
+    match prefix.strip_prefix('#') {
+        // digits can't start an identifier
+        Some(id) if id.is_empty() || is_ident(&format!("{id}a")) => Ok(()),
+        _ => Err(format!(
+            "`{prefix}` is not an id selector prefix like `#item-`"
+        )),
+    }
+}
+
+pub(crate) fn check_selector(selector: &str) -> Result<(), String> {
+    let mut parser = Parser {
+        chars: selector.char_indices().peekable(),
+    };
+    parser
+        .selector_list()
+        .map_err(|e| format!("`{selector}` is not a valid selector: {e}"))
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn selector_list(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_whitespace();
+            self.complex()?;
+            match self.chars.next() {
+                None => return Ok(()),
+                Some((_, ',')) => continue,
+                Some((i, c)) => return Err(format!("unexpected `{c}` at {i}")),
+            }
+        }
+    }
+
+    /// compound selectors joined by combinators
+    fn complex(&mut self) -> Result<(), String> {
+        self.compound()?;
+        loop {
+            let spaced = self.skip_whitespace();
+            match self.peek() {
+                None | Some(',') => return Ok(()),
+                Some('>' | '+' | '~') => {
+                    self.chars.next();
+                    self.skip_whitespace();
+                }
+                Some(_) if spaced => {}
+                Some(c) => return Err(self.unexpected(c)),
+            }
+            self.compound()?;
+        }
+    }
+
+    fn compound(&mut self) -> Result<(), String> {
+        let mut empty = true;
+        match self.peek() {
+            Some('*') => {
+                self.chars.next();
+                empty = false;
+            }
+            Some(c) if is_ident_start(c) || c == '-' => {
+                self.ident()?;
+                empty = false;
+            }
+            _ => {}
+        }
+        loop {
+            match self.peek() {
+                Some('#' | '.') => {
+                    self.chars.next();
+                    self.ident()?;
+                }
+                Some('[') => {
+                    self.chars.next();
+                    self.attribute()?;
+                }
+                Some(c) if empty => return Err(self.unexpected(c)),
+                None if empty => return Err("expected a selector".to_string()),
+                _ => return Ok(()),
+            }
+            empty = false;
+        }
+    }
+
+    /// after `[`: `name`, `name=value`, `name~="value"`...
+    fn attribute(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        self.ident()?;
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((_, ']')) => return Ok(()),
+            Some((_, '=')) => {}
+            Some((_, '~' | '|' | '^' | '$' | '*')) if self.peek() == Some('=') => {
+                self.chars.next();
+            }
+            Some((i, c)) => return Err(format!("unexpected `{c}` at {i}")),
+            None => return Err("unclosed `[`".to_string()),
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.chars.next();
+                if !self.chars.by_ref().any(|(_, c)| c == quote) {
+                    return Err(format!("unclosed `{quote}`"));
+                }
+            }
+            _ => self.ident()?,
+        }
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((_, ']')) => Ok(()),
+            Some((i, c)) => Err(format!("unexpected `{c}` at {i}")),
+            None => Err("unclosed `[`".to_string()),
+        }
+    }
+
+    fn ident(&mut self) -> Result<(), String> {
+        let start = match self.chars.peek() {
+            Some(&(i, _)) => i,
+            None => return Err("expected an identifier".to_string()),
+        };
+        let mut ident = String::new();
+        while let Some(c) = self.peek().filter(|&c| is_ident_char(c)) {
+            ident.push(c);
+            self.chars.next();
+        }
+        if is_ident(&ident) {
+            return Ok(());
+        }
+        match ident.chars().next() {
+            Some(c) => Err(format!(
+                "invalid identifier `{ident}` at {start}, `{c}` can't start it"
+            )),
+            None => {
+                let c = self.peek().unwrap_or_default();
+                Err(self.unexpected(c))
+            }
+        }
+    }
+
+    /// skip whitespace, returns whether there was any
+    fn skip_whitespace(&mut self) -> bool {
+        let mut skipped = false;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.chars.next();
+            skipped = true;
+        }
+        skipped
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn unexpected(&mut self, c: char) -> String {
+        let i = self.chars.peek().map_or(0, |&(i, _)| i);
+        format!("unexpected `{c}` at {i}")
+    }
+}
+
+/// a CSS identifier, without escapes: `-`? (letter | `_` | non-ascii) (letter | digit | `_` | `-`
+/// | non-ascii)*
+fn is_ident(s: &str) -> bool {
+    let rest = s.strip_prefix('-').unwrap_or(s);
+    let mut chars = rest.chars();
+    match chars.next() {
+        Some(c) if is_ident_start(c) || (c == '-' && s.starts_with('-')) => {
+            chars.all(is_ident_char)
+        }
+        _ => false,
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_ident_char(c: char) -> bool {
+    is_ident_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_receivers_should_skip_empty_parts() {
+        let receivers = split_receivers(" #a  #b,#c , \n#d,").collect::<Vec<_>>();
+        assert_eq!(receivers, ["#a", "#b", "#c", "#d"]);
+    }
+
+    #[test]
+    fn id_selectors_should_be_checked() {
+        for ok in ["#a", "#room-42", "#_x", "#-a", "#--a", "#中文"] {
+            assert!(check_id_selector(ok).is_ok(), "{ok}");
+        }
+        for bad in ["a", "#", "#1a", "#a.b", "#a b", ".a", "#-1"] {
+            assert!(check_id_selector(bad).is_err(), "{bad}");
+        }
+        assert_eq!(
+            check_id_selector("#1a").unwrap_err(),
+            "`#1a` is not an id selector like `#foo`"
+        );
+    }
+
+    #[test]
+    fn id_prefixes_should_be_checked() {
+        for ok in ["#", "#room-", "#id_", "#a1"] {
+            assert!(check_id_prefix(ok).is_ok(), "{ok}");
+        }
+        for bad in ["room-", "#1", "#a.", "#a b"] {
+            assert!(check_id_prefix(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn selectors_should_be_checked() {
+        let ok = [
+            "#a",
+            ".a",
+            "li",
+            "*",
+            "ul#list > li.item",
+            "#a .b ~ .c + d",
+            "#a>.b",
+            "[data-id]",
+            "a[href^='https://']",
+            r#"input[ type = "text" ]"#,
+            "li[data-x|=en]",
+            "#a, .b",
+        ];
+        for s in ok {
+            assert!(check_selector(s).is_ok(), "{s}: {:?}", check_selector(s));
+        }
+        let bad = [
+            ("", "`` is not a valid selector: expected a selector"),
+            ("#", "`#` is not a valid selector: expected an identifier"),
+            (
+                "#1a",
+                "`#1a` is not a valid selector: invalid identifier `1a` at 1, `1` can't start it",
+            ),
+            ("#a,", "`#a,` is not a valid selector: expected a selector"),
+            (
+                "#a >",
+                "`#a >` is not a valid selector: expected a selector",
+            ),
+            ("[x", "`[x` is not a valid selector: unclosed `[`"),
+            ("[x='a]", "`[x='a]` is not a valid selector: unclosed `'`"),
+            (
+                "a:hover",
+                "`a:hover` is not a valid selector: unexpected `:` at 1",
+            ),
+        ];
+        for (s, err) in bad {
+            assert_eq!(check_selector(s).unwrap_err(), err, "{s}");
+        }
+    }
+}
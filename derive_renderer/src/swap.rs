@@ -0,0 +1,136 @@
+use crate::event::error;
+use darling::util::SpannedValue;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+/// htmx swap styles and the `jinja_renderer::Swap` variants they map to
+const STYLES: &[(&str, &str)] = &[
+    ("innerHTML", "InnerHtml"),
+    ("outerHTML", "OuterHtml"),
+    ("beforebegin", "BeforeBegin"),
+    ("afterbegin", "AfterBegin"),
+    ("beforeend", "BeforeEnd"),
+    ("afterend", "AfterEnd"),
+    ("delete", "Delete"),
+    ("none", "None"),
+];
+
+/// check `swap = "style modifiers..."` and generate the `SwapSpec` for it
+pub(crate) fn generate_swap(swap: &SpannedValue<String>) -> darling::Result<TokenStream> {
+    let span = swap.span();
+    let mut parts = swap.split_whitespace();
+    let style = parts.next().unwrap_or_default();
+    let Some((_, variant)) = STYLES.iter().find(|(name, _)| *name == style) else {
+        let names = STYLES.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        return Err(error(
+            span,
+            format!(
+                "unknown swap style `{style}`, expected one of {}",
+                names.join(", ")
+            ),
+        ));
+    };
+
+    let mut errors = darling::Error::accumulator();
+    let modifiers = parts
+        .filter(|m| errors.handle(check_modifier(m, span)).is_some())
+        .collect::<Vec<_>>()
+        .join(" ");
+    errors.finish()?;
+
+    let variant = Ident::new(variant, Span::call_site());
+    Ok(quote! {
+        jinja_renderer::SwapSpec::new(jinja_renderer::Swap::#variant, #modifiers)
+    })
+}
+
+fn check_modifier(modifier: &str, span: Span) -> darling::Result<()> {
+    let invalid = |example: &str| {
+        Err(error(
+            span,
+            format!("invalid swap modifier `{modifier}`, expected e.g. `{example}`"),
+        ))
+    };
+    let Some((key, value)) = modifier.split_once(':') else {
+        return Err(error(span, format!("unknown swap modifier `{modifier}`")));
+    };
+
+    match key {
+        "swap" | "settle" if !is_interval(value) => invalid(&format!("{key}:100ms")),
+        "scroll" | "show" if !is_position(key, value) => invalid(&format!("{key}:top")),
+        "focus-scroll" | "ignoreTitle" | "transition" if !matches!(value, "true" | "false") => {
+            invalid(&format!("{key}:true"))
+        }
+        "swap" | "settle" | "scroll" | "show" | "focus-scroll" | "ignoreTitle" | "transition" => {
+            Ok(())
+        }
+        _ => Err(error(span, format!("unknown swap modifier `{modifier}`"))),
+    }
+}
+
+/// `100`, `100ms` or `1s`
+fn is_interval(value: &str) -> bool {
+    let digits = value
+        .strip_suffix("ms")
+        .or_else(|| value.strip_suffix('s'))
+        .unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `top`, `bottom` or `selector:top`, `show` can also be `none`
+fn is_position(key: &str, value: &str) -> bool {
+    if key == "show" && value == "none" {
+        return true;
+    }
+    let position = value.rsplit_once(':').map_or(value, |(_, p)| p);
+    matches!(position, "top" | "bottom")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_with_modifiers_should_work() {
+        let swap = SpannedValue::new(
+            "beforeend  scroll:#list:bottom settle:100ms show:none transition:true".to_string(),
+            Span::call_site(),
+        );
+        let expected = quote! {
+            jinja_renderer::SwapSpec::new(jinja_renderer::Swap::BeforeEnd, "scroll:#list:bottom settle:100ms show:none transition:true")
+        };
+        assert_eq!(
+            generate_swap(&swap).unwrap().to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn unknown_swap_style_should_fail() {
+        let swap = SpannedValue::new("innerHtml".to_string(), Span::call_site());
+        let err = generate_swap(&swap).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown swap style `innerHtml`, expected one of innerHTML, outerHTML, beforebegin, afterbegin, beforeend, afterend, delete, none"
+        );
+    }
+
+    #[test]
+    fn invalid_swap_modifiers_should_fail() {
+        let swap = SpannedValue::new(
+            "innerHTML settle:fast scroll:middle focus:true ignoreTitle:yes".to_string(),
+            Span::call_site(),
+        );
+        let err = generate_swap(&swap).unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "invalid swap modifier `settle:fast`, expected e.g. `settle:100ms`",
+                "invalid swap modifier `scroll:middle`, expected e.g. `scroll:top`",
+                "unknown swap modifier `focus:true`",
+                "invalid swap modifier `ignoreTitle:yes`, expected e.g. `ignoreTitle:true`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,6 @@
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    // this crate has no `serde_json` dependency, so the generated code must not need one
+    t.pass("tests/ui/event_without_serde_json.rs");
+}
@@ -0,0 +1,21 @@
+use jinja_renderer::{Event, RenderEvent, Renderer, Template};
+use serde::Serialize;
+
+#[derive(Serialize, Template, Event)]
+#[template(source = "<li>{{ name }}</li>")]
+#[event(name = "item_added", receivers = "#list", target = "#list", swap = "beforeend")]
+struct ItemAdded {
+    id: u64,
+    name: String,
+}
+
+fn main() {
+    let event = ItemAdded {
+        id: 1,
+        name: "foo".to_string(),
+    };
+    let mut renderer = Renderer::default();
+    renderer.add_inline_template::<ItemAdded>().unwrap();
+    let data = event.render_event_data(&renderer).unwrap();
+    assert!(data.ends_with("\n<li>foo</li>"));
+}
@@ -0,0 +1,61 @@
+//! Helpers for the code generated by the derive macros, not a public API.
+
+use crate::{rendered, Error, EventInfo, RenderedEvent};
+use serde::Serialize;
+
+/// the JSON part of the event data: `{"info": ..., "data": ...}`
+pub fn event_json<T: Serialize + ?Sized>(info: &EventInfo, data: &T) -> Result<String, Error> {
+    #[derive(Serialize)]
+    struct Payload<'a, T: ?Sized> {
+        info: &'a EventInfo,
+        data: &'a T,
+    }
+
+    rendered::to_json(info.name, &Payload { info, data })
+}
+
+pub fn rendered_event<T: Serialize + ?Sized>(
+    info: EventInfo,
+    data: &T,
+    html: Option<String>,
+) -> Result<RenderedEvent, Error> {
+    let data = rendered::to_raw_value(info.name, data)?;
+    Ok(RenderedEvent {
+        id: None,
+        info,
+        data,
+        html,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::info, ErrorKind};
+    use serde::ser::Error as _;
+
+    struct Broken;
+
+    impl Serialize for Broken {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("broken"))
+        }
+    }
+
+    #[test]
+    fn event_json_should_return_errors() {
+        let info = info("foo", &["#foo"]);
+        let ret = event_json(&info, &[1, 2]).unwrap();
+        assert_eq!(
+            ret,
+            r##"{"info":{"name":"foo","receivers":["#foo"],"target":"#list","swap":"beforeend","idField":"id"},"data":[1,2]}"##
+        );
+
+        let err = event_json(&info, &Broken).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadSerialization);
+        assert_eq!(err.detail(), Some("failed to serialize event foo"));
+
+        let err = rendered_event(info, &Broken, None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadSerialization);
+    }
+}
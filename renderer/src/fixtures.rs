@@ -0,0 +1,65 @@
+use crate::{__private, Error, EventInfo, RenderEvent, Renderer, Swap};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// an event `name` for `receivers`, appended to `#list`
+pub(crate) fn info(name: &'static str, receivers: &[&'static str]) -> EventInfo {
+    EventInfo {
+        name,
+        receivers: receivers.iter().map(|&r| r.into()).collect(),
+        target: "#list".into(),
+        swap: Swap::BeforeEnd.into(),
+        id_field: "id",
+    }
+}
+
+/// An event rendered like a derived one, its data is `user` and its HTML `<li>{user}</li>`
+#[derive(Debug, Clone)]
+pub(crate) struct TestEvent {
+    pub(crate) info: EventInfo,
+    pub(crate) user: &'static str,
+    pub(crate) html: Option<String>,
+    renders: Arc<AtomicUsize>,
+}
+
+impl TestEvent {
+    pub(crate) fn new(info: EventInfo, user: &'static str) -> Self {
+        Self {
+            info,
+            user,
+            html: Some(format!("<li>{user}</li>")),
+            renders: Default::default(),
+        }
+    }
+
+    /// how many times the event was rendered
+    #[cfg(feature = "hub")]
+    pub(crate) fn renders(&self) -> usize {
+        self.renders.load(Ordering::SeqCst)
+    }
+}
+
+impl RenderEvent for TestEvent {
+    // like an enum of events, the name comes from `event_info`
+    const EVENT_NAME: &'static str = "";
+
+    fn render_event_data(&self, _renderer: &Renderer) -> Result<String, Error> {
+        self.renders.fetch_add(1, Ordering::SeqCst);
+        let mut ret = __private::event_json(&self.info, self.user)?;
+        if let Some(html) = &self.html {
+            ret.push('\n');
+            ret.push_str(html);
+        }
+        Ok(ret)
+    }
+
+    fn event_info(&self) -> EventInfo {
+        self.info.clone()
+    }
+
+    fn render_html(&self, _renderer: &Renderer) -> Result<Option<String>, Error> {
+        Ok(self.html.clone())
+    }
+}
@@ -9,24 +9,374 @@ use http_02 as http;
 #[cfg(feature = "with-axum-06")]
 use http_02::StatusCode;
 
-use crate::{RenderContext, Renderer};
+use crate::{Error, RenderContext, Renderer};
+#[cfg(any(
+    feature = "with-axum-ws",
+    all(feature = "hub", feature = "with-axum-sse")
+))]
+use crate::{EventFormat, Subscription};
+use http::{header, HeaderMap, HeaderValue};
 use tracing::warn;
+#[cfg(any(feature = "with-axum-sse", feature = "with-axum-ws"))]
+use {crate::RenderEvent, futures_util::StreamExt};
+#[cfg(feature = "with-axum-sse")]
+use {
+    crate::{sse::lines, SseFrame},
+    axum::response::sse::{Event, KeepAlive, Sse},
+    futures_util::future,
+    std::convert::Infallible,
+};
+#[cfg(all(feature = "hub", feature = "with-axum-sse"))]
+use {
+    crate::{EventHub, RenderedEvent},
+    futures_util::stream,
+};
+#[cfg(feature = "with-axum-ws")]
+use {
+    axum::extract::ws::{Message, WebSocket},
+    std::pin::pin,
+};
+#[cfg(feature = "with-axum")]
+use {
+    axum_core::body::Body,
+    bytes::Bytes,
+    futures_core::Stream,
+    std::{
+        io,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tokio::sync::mpsc,
+};
+
+const HX_REQUEST: &str = "hx-request";
+const HX_TARGET: &str = "hx-target";
+const HX_HISTORY_RESTORE_REQUEST: &str = "hx-history-restore-request";
+#[cfg(all(feature = "hub", feature = "with-axum-sse"))]
+const LAST_EVENT_ID: &str = "last-event-id";
+/// streamed responses are sent in chunks of this size
+#[cfg(feature = "with-axum")]
+const CHUNK_SIZE: usize = 16 * 1024;
 
 impl Renderer {
     pub fn render_response<T: RenderContext>(&self, data: &T) -> Response {
-        match data.render(self) {
-            Ok(body) => {
-                let headers = [(
-                    http::header::CONTENT_TYPE,
-                    http::HeaderValue::from_static(T::MIME_TYPE),
-                )];
-
-                (headers, body).into_response()
+        into_response::<T>(data.render(self), [])
+    }
+
+    /// render `T::PARTIAL_BLOCK` for htmx requests and the full template otherwise
+    pub fn render_htmx_response<T: RenderContext>(
+        &self,
+        headers: &HeaderMap,
+        data: &T,
+    ) -> Response {
+        let ret = match partial_block::<T>(headers) {
+            Some(block) => data.render_block(self, block),
+            None => data.render(self),
+        };
+        // the same url returns different content depending on HX-Request
+        let vary = (header::VARY, HeaderValue::from_static("HX-Request"));
+        into_response::<T>(ret, [vary])
+    }
+
+    /// stream the rendered template as the response body, so large pages are never buffered
+    /// whole. Rendering runs on the blocking thread pool; it fails with 500 if nothing was
+    /// rendered yet, and aborts the body otherwise.
+    #[cfg(feature = "with-axum")]
+    pub async fn render_stream_response<T>(self: &Arc<Self>, data: T) -> Response
+    where
+        T: RenderContext + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel(4);
+        let renderer = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ChunkWriter::new(tx);
+            let ret = data
+                .render_to_writer(&renderer, &mut writer)
+                .and_then(|_| writer.finish());
+            if let Err(e) = ret {
+                writer.fail(e);
+            }
+        });
+
+        let first = match rx.recv().await {
+            Some(Ok(chunk)) => Some(chunk),
+            None => None,
+            Some(Err(e)) => return into_response::<T>(Err::<Body, _>(e), []),
+        };
+        let body = Body::from_stream(ChunkStream { first, rx });
+        into_response::<T>(Ok(body), [])
+    }
+
+    /// turn a stream of events into an SSE response with keep-alive. Events that fail to render
+    /// are logged and skipped.
+    #[cfg(feature = "with-axum-sse")]
+    pub fn render_sse<S>(
+        self: &Arc<Self>,
+        events: S,
+    ) -> Sse<impl Stream<Item = Result<Event, Infallible>>>
+    where
+        S: Stream + Send + 'static,
+        S::Item: RenderEvent,
+    {
+        let renderer = self.clone();
+        let stream =
+            events.filter_map(move |event| future::ready(sse_event(&renderer, &event).map(Ok)));
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    /// send the events the client subscribed to over the WebSocket, one text frame per event
+    /// encoded in `format`, until the events end or the client closes the socket
+    #[cfg(feature = "with-axum-ws")]
+    pub async fn render_ws<S>(
+        &self,
+        mut socket: WebSocket,
+        events: S,
+        subscription: &Subscription,
+        format: EventFormat,
+    ) -> Result<(), axum::Error>
+    where
+        S: Stream,
+        S::Item: RenderEvent,
+    {
+        let mut events = pin!(events);
+        loop {
+            tokio::select! {
+                event = events.next() => {
+                    let Some(event) = event else { break };
+                    if let Some(text) = ws_message(self, &event, subscription, format) {
+                        socket.send(Message::Text(text)).await?;
+                    }
+                }
+                // reading answers pings and the close handshake, other messages are ignored
+                msg = socket.recv() => match msg {
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Err(e)) => return Err(e),
+                    Some(Ok(_)) => {}
+                },
             }
-            Err(e) => {
-                warn!("failed to render response: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        socket.close().await
+    }
+}
+
+#[cfg(all(feature = "hub", feature = "with-axum-sse"))]
+impl EventHub {
+    /// turn the events received from the hub into an SSE response with keep-alive, their data
+    /// encoded in `format`. The event ids are sent along, for the browser to send the last one
+    /// back as `Last-Event-ID` when it reconnects.
+    pub fn sse<S>(
+        events: S,
+        format: EventFormat,
+    ) -> Sse<impl Stream<Item = Result<Event, Infallible>>>
+    where
+        S: Stream<Item = Arc<RenderedEvent>> + Send + 'static,
+    {
+        let stream = events.filter_map(move |event| {
+            let ret = match event.to_sse_frame(format) {
+                Ok(frame) => Some(Ok(frame_event(frame))),
+                Err(e) => {
+                    warn!("failed to encode event {}: {}", event.info.name, e);
+                    None
+                }
+            };
+            future::ready(ret)
+        });
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    /// subscribe an SSE client to the events matching the subscription. A reconnecting client
+    /// first gets the events it missed after its `Last-Event-ID`, or only the next ones if they
+    /// aren't kept anymore, see [`EventHub::resubscribe`].
+    pub fn subscribe_sse(
+        &self,
+        headers: &HeaderMap,
+        subscription: Subscription,
+        format: EventFormat,
+    ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+        let (replay, rx) = match last_event_id(headers) {
+            Some(last_id) => {
+                let (replay, rx) = self.resubscribe(subscription, last_id);
+                if replay.is_none() {
+                    warn!("events after {last_id} aren't kept anymore, they can't be replayed");
+                }
+                (replay.unwrap_or_default(), rx)
             }
+            None => (Vec::new(), self.subscribe(subscription)),
+        };
+        let live = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|e| (e, rx)) });
+        Self::sse(stream::iter(replay).chain(live), format)
+    }
+}
+
+/// sends what's written in chunks of `CHUNK_SIZE` to the response body
+#[cfg(feature = "with-axum")]
+struct ChunkWriter {
+    buf: Vec<u8>,
+    tx: mpsc::Sender<Result<Bytes, Error>>,
+}
+
+#[cfg(feature = "with-axum")]
+impl ChunkWriter {
+    fn new(tx: mpsc::Sender<Result<Bytes, Error>>) -> Self {
+        Self {
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            tx,
+        }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        let chunk = Bytes::from(std::mem::replace(
+            &mut self.buf,
+            Vec::with_capacity(CHUNK_SIZE),
+        ));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        self.send().map_err(|e| {
+            Error::new(crate::ErrorKind::WriteFailure, "failed to send response").with_source(e)
+        })
+    }
+
+    fn fail(self, e: Error) {
+        // the client went away, nobody is waiting for the error
+        if self.tx.is_closed() {
+            return;
+        }
+        let _ = self.tx.blocking_send(Err(e));
+    }
+}
+
+#[cfg(feature = "with-axum")]
+impl io::Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "with-axum")]
+struct ChunkStream {
+    first: Option<Bytes>,
+    rx: mpsc::Receiver<Result<Bytes, Error>>,
+}
+
+#[cfg(feature = "with-axum")]
+impl Stream for ChunkStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(chunk) = self.first.take() {
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+        let ret = self.rx.poll_recv(cx);
+        if let Poll::Ready(Some(Err(e))) = &ret {
+            warn!("failed to render response: {}", e);
+        }
+        ret
+    }
+}
+
+#[cfg(feature = "with-axum-sse")]
+fn sse_event<E: RenderEvent>(renderer: &Renderer, event: &E) -> Option<Event> {
+    match event.to_sse_frame(renderer) {
+        Ok(frame) => Some(frame_event(frame)),
+        Err(e) => {
+            warn!("failed to render event {}: {}", event.event_info().name, e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "with-axum-sse")]
+fn frame_event(frame: SseFrame) -> Event {
+    // axum rejects CR in the data, normalize the line endings
+    let data = lines(&frame.data).collect::<Vec<_>>().join("\n");
+    let mut event = Event::default().event(frame.event).data(data);
+    if let Some(id) = frame.id {
+        event = event.id(id);
+    }
+    if let Some(retry) = frame.retry {
+        event = event.retry(retry);
+    }
+    event
+}
+
+/// the id of the last event a reconnecting SSE client got
+#[cfg(all(feature = "hub", feature = "with-axum-sse"))]
+fn last_event_id(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(LAST_EVENT_ID)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(feature = "with-axum-ws")]
+fn ws_message<E: RenderEvent>(
+    renderer: &Renderer,
+    event: &E,
+    subscription: &Subscription,
+    format: EventFormat,
+) -> Option<String> {
+    let info = event.event_info();
+    if !subscription.matches(&info) {
+        return None;
+    }
+    match event.render_event(renderer).and_then(|e| e.encode(format)) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            warn!("failed to render event {}: {}", info.name, e);
+            None
+        }
+    }
+}
+
+/// the block to render for the request, if it's an htmx request for a partial update
+fn partial_block<T: RenderContext>(headers: &HeaderMap) -> Option<&'static str> {
+    let block = T::PARTIAL_BLOCK?;
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+
+    // history restore needs the full page even though it's sent by htmx
+    if header(HX_REQUEST) != Some("true") || header(HX_HISTORY_RESTORE_REQUEST) == Some("true") {
+        return None;
+    }
+    match T::PARTIAL_TARGET {
+        Some(target) if header(HX_TARGET) != Some(target) => None,
+        _ => Some(block),
+    }
+}
+
+fn into_response<T: RenderContext>(
+    ret: Result<impl IntoResponse, Error>,
+    extra: impl IntoIterator<Item = (header::HeaderName, HeaderValue)>,
+) -> Response {
+    match ret {
+        Ok(body) => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(T::MIME_TYPE));
+            headers.extend(extra);
+
+            (headers, body).into_response()
+        }
+        Err(e) => {
+            warn!("failed to render response: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
@@ -0,0 +1,341 @@
+use crate::{Error, RenderEvent, RenderedEvent, Renderer, Subscription};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::warn;
+
+/// What to do with a subscriber whose buffer is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// skip the event for the subscriber, it gets the next ones once it catches up
+    #[default]
+    Skip,
+    /// drop the subscriber: its receiver ends after the buffered events, so the client reconnects
+    Disconnect,
+}
+
+/// Renders each published event once and fans it out to the subscribers of its name or receivers.
+/// Published events get increasing ids, the last ones can be kept to replay them to reconnecting
+/// clients, see [`EventHub::with_history`].
+#[derive(Debug)]
+pub struct EventHub {
+    renderer: Arc<Renderer>,
+    capacity: usize,
+    policy: LagPolicy,
+    history: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    subscribers: Vec<Subscriber>,
+    // the last `history` published events, oldest first
+    replay: VecDeque<Arc<RenderedEvent>>,
+    last_id: u64,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    subscription: Subscription,
+    tx: mpsc::Sender<Arc<RenderedEvent>>,
+}
+
+impl EventHub {
+    /// every subscriber buffers up to `capacity` events, it panics if `capacity` is 0
+    pub fn new(renderer: Arc<Renderer>, capacity: usize) -> Self {
+        assert!(capacity > 0, "event hub capacity must be greater than 0");
+        Self {
+            renderer,
+            capacity,
+            policy: LagPolicy::default(),
+            history: 0,
+            state: Default::default(),
+        }
+    }
+
+    pub fn with_lag_policy(mut self, policy: LagPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// keep the last `history` published events for [`EventHub::replay`]. Events are rendered
+    /// even if nobody subscribed to them then.
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// receive the events matching the subscription. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self, subscription: Subscription) -> mpsc::Receiver<Arc<RenderedEvent>> {
+        let mut state = self.state.lock().unwrap();
+        self.add_subscriber(&mut state, subscription)
+    }
+
+    /// subscribe a reconnecting client, with the events it missed after `last_id`, usually its
+    /// `Last-Event-ID`. Nothing is missed or received twice between the replay and the receiver.
+    pub fn resubscribe(
+        &self,
+        subscription: Subscription,
+        last_id: u64,
+    ) -> (
+        Option<Vec<Arc<RenderedEvent>>>,
+        mpsc::Receiver<Arc<RenderedEvent>>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let replay = replay(&state, &subscription, last_id);
+        (replay, self.add_subscriber(&mut state, subscription))
+    }
+
+    /// the events matching the subscription published after `last_id`, oldest first. `None` if
+    /// some of them aren't kept anymore or `last_id` is unknown, the client should reload then.
+    pub fn replay(
+        &self,
+        subscription: &Subscription,
+        last_id: u64,
+    ) -> Option<Vec<Arc<RenderedEvent>>> {
+        replay(&self.state.lock().unwrap(), subscription, last_id)
+    }
+
+    /// the id of the last published event, 0 if there is none
+    pub fn last_id(&self) -> u64 {
+        self.state.lock().unwrap().last_id
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.subscribers.retain(|s| !s.tx.is_closed());
+        state.subscribers.len()
+    }
+
+    /// render the event, give it the next id and send it to its subscribers, returns how many of
+    /// them got it. Without history, the event isn't rendered if nobody subscribed to it.
+    pub fn publish<E: RenderEvent>(&self, event: &E) -> Result<usize, Error> {
+        let info = event.event_info();
+        if self.history == 0 {
+            let state = self.state.lock().unwrap();
+            if !state
+                .subscribers
+                .iter()
+                .any(|s| s.subscription.matches(&info))
+            {
+                return Ok(0);
+            }
+        }
+
+        // render without holding the lock, it's the slow part
+        let mut rendered = event.render_event(&self.renderer)?;
+        let mut state = self.state.lock().unwrap();
+        state.last_id += 1;
+        rendered.id = Some(state.last_id);
+        let rendered = Arc::new(rendered);
+        if self.history > 0 {
+            if state.replay.len() == self.history {
+                state.replay.pop_front();
+            }
+            state.replay.push_back(rendered.clone());
+        }
+
+        let mut sent = 0;
+        state.subscribers.retain(|s| {
+            if !s.subscription.matches(&info) {
+                return !s.tx.is_closed();
+            }
+            match s.tx.try_send(rendered.clone()) {
+                Ok(()) => {
+                    sent += 1;
+                    true
+                }
+                Err(TrySendError::Full(_)) => {
+                    warn!("subscriber lagging behind, event {} not sent", info.name);
+                    self.policy == LagPolicy::Skip
+                }
+                Err(TrySendError::Closed(_)) => false,
+            }
+        });
+        Ok(sent)
+    }
+
+    fn add_subscriber(
+        &self,
+        state: &mut State,
+        subscription: Subscription,
+    ) -> mpsc::Receiver<Arc<RenderedEvent>> {
+        let (tx, rx) = mpsc::channel(self.capacity);
+        state.subscribers.push(Subscriber { subscription, tx });
+        rx
+    }
+}
+
+fn replay(
+    state: &State,
+    subscription: &Subscription,
+    last_id: u64,
+) -> Option<Vec<Arc<RenderedEvent>>> {
+    // the kept events have consecutive ids up to `state.last_id`
+    let oldest = state
+        .replay
+        .front()
+        .and_then(|e| e.id)
+        .unwrap_or(state.last_id + 1);
+    if last_id > state.last_id || last_id + 1 < oldest {
+        return None;
+    }
+    let events = state
+        .replay
+        .iter()
+        .filter(|e| e.id > Some(last_id) && subscription.matches(&e.info))
+        .cloned()
+        .collect();
+    Some(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{info, TestEvent};
+
+    fn joined(room: &'static str, user: &'static str) -> TestEvent {
+        TestEvent::new(info("joined", &[room]), user)
+    }
+
+    fn new_hub(capacity: usize) -> EventHub {
+        EventHub::new(Arc::new(Renderer::default()), capacity)
+    }
+
+    #[tokio::test]
+    async fn hub_should_fan_out_by_name_and_receiver() {
+        let hub = new_hub(8);
+        let mut room1 = hub.subscribe(Subscription::new(["#room-1"]));
+        let mut room2 = hub.subscribe(Subscription::new(["#room-2"]));
+        let mut all = hub.subscribe(Subscription::new(["joined"]));
+
+        let alice = joined("#room-1", "alice");
+        assert_eq!(hub.publish(&alice).unwrap(), 2);
+        // rendered once for both subscribers
+        assert_eq!(alice.renders(), 1);
+        assert_eq!(hub.publish(&joined("#room-2", "bob")).unwrap(), 2);
+
+        let event = room1.recv().await.unwrap();
+        assert_eq!(event.data.get(), "\"alice\"");
+        assert_eq!(event.html.as_deref(), Some("<li>alice</li>"));
+        assert!(room1.try_recv().is_err());
+        assert_eq!(room2.recv().await.unwrap().data.get(), "\"bob\"");
+        assert_eq!(all.recv().await.unwrap().data.get(), "\"alice\"");
+        assert_eq!(all.recv().await.unwrap().data.get(), "\"bob\"");
+    }
+
+    #[tokio::test]
+    async fn hub_should_not_render_unsubscribed_events() {
+        let hub = new_hub(8);
+        let _rx = hub.subscribe(Subscription::new(["#room-1"]));
+        let carol = joined("#room-3", "carol");
+        assert_eq!(hub.publish(&carol).unwrap(), 0);
+        assert_eq!(carol.renders(), 0);
+    }
+
+    #[tokio::test]
+    async fn lagging_subscriber_should_skip_events() {
+        let hub = new_hub(1);
+        let mut slow = hub.subscribe(Subscription::new(["joined"]));
+        assert_eq!(hub.publish(&joined("#room-1", "alice")).unwrap(), 1);
+        assert_eq!(hub.publish(&joined("#room-1", "bob")).unwrap(), 0);
+        assert_eq!(slow.recv().await.unwrap().data.get(), "\"alice\"");
+        assert_eq!(hub.publish(&joined("#room-1", "carol")).unwrap(), 1);
+        assert_eq!(slow.recv().await.unwrap().data.get(), "\"carol\"");
+        assert_eq!(hub.subscriber_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn lagging_subscriber_should_be_disconnected() {
+        let hub = new_hub(1).with_lag_policy(LagPolicy::Disconnect);
+        let mut slow = hub.subscribe(Subscription::new(["joined"]));
+        let mut fast = hub.subscribe(Subscription::new(["joined"]));
+        assert_eq!(hub.publish(&joined("#room-1", "alice")).unwrap(), 2);
+        assert_eq!(fast.recv().await.unwrap().data.get(), "\"alice\"");
+        assert_eq!(hub.publish(&joined("#room-1", "bob")).unwrap(), 1);
+        assert_eq!(hub.subscriber_count(), 1);
+
+        // the buffered event is still delivered before the receiver ends
+        assert_eq!(slow.recv().await.unwrap().data.get(), "\"alice\"");
+        assert!(slow.recv().await.is_none());
+        assert_eq!(fast.recv().await.unwrap().data.get(), "\"bob\"");
+    }
+
+    #[tokio::test]
+    async fn published_events_should_get_increasing_ids() {
+        let hub = new_hub(8);
+        // not rendered, so no id either
+        assert_eq!(hub.publish(&joined("#room-1", "alice")).unwrap(), 0);
+        assert_eq!(hub.last_id(), 0);
+
+        let mut rx = hub.subscribe(Subscription::new(["joined"]));
+        let mut rx2 = hub.subscribe(Subscription::new(["#room-2"]));
+        hub.publish(&joined("#room-1", "alice")).unwrap();
+        hub.publish(&joined("#room-2", "bob")).unwrap();
+        assert_eq!(rx.recv().await.unwrap().id, Some(1));
+        assert_eq!(rx.recv().await.unwrap().id, Some(2));
+        assert_eq!(rx2.recv().await.unwrap().id, Some(2));
+        assert_eq!(hub.last_id(), 2);
+    }
+
+    #[tokio::test]
+    async fn hub_should_replay_events_after_an_id() {
+        let hub = new_hub(8).with_history(3);
+        for user in ["a", "b", "c", "d"] {
+            // recorded even without subscribers
+            assert_eq!(hub.publish(&joined("#room-1", user)).unwrap(), 0);
+        }
+        hub.publish(&joined("#room-2", "e")).unwrap();
+        assert_eq!(hub.last_id(), 5);
+
+        let room1 = Subscription::new(["#room-1"]);
+        let data = |events: Option<Vec<Arc<RenderedEvent>>>| {
+            events.map(|events| {
+                let data = events.iter().map(|e| serde_json::from_str(e.data.get()));
+                data.collect::<Result<Vec<String>, _>>().unwrap()
+            })
+        };
+        assert_eq!(
+            data(hub.replay(&room1, 2)),
+            Some(vec!["c".into(), "d".into()])
+        );
+        assert_eq!(data(hub.replay(&room1, 3)), Some(vec!["d".into()]));
+        assert_eq!(data(hub.replay(&room1, 5)), Some(vec![]));
+        // 2 fell out of the buffer, unknown ids need a reload too
+        assert_eq!(data(hub.replay(&room1, 1)), None);
+        assert_eq!(data(hub.replay(&room1, 6)), None);
+    }
+
+    #[tokio::test]
+    async fn resubscribe_should_continue_after_the_replay() {
+        let hub = new_hub(8).with_history(8);
+        hub.publish(&joined("#room-1", "alice")).unwrap();
+        hub.publish(&joined("#room-1", "bob")).unwrap();
+
+        let (replay, mut rx) = hub.resubscribe(Subscription::new(["joined"]), 1);
+        let replay = replay.unwrap();
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].id, Some(2));
+        hub.publish(&joined("#room-1", "carol")).unwrap();
+        assert_eq!(rx.recv().await.unwrap().id, Some(3));
+        assert!(rx.try_recv().is_err());
+
+        // nothing published yet
+        let hub = new_hub(8).with_history(8);
+        let (replay, _rx) = hub.resubscribe(Subscription::new(["joined"]), 0);
+        assert_eq!(replay.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_receivers_should_unsubscribe() {
+        let hub = new_hub(8);
+        let rx = hub.subscribe(Subscription::new(["joined"]));
+        let _other = hub.subscribe(Subscription::new(["#room-9"]));
+        assert_eq!(hub.subscriber_count(), 2);
+        drop(rx);
+        assert_eq!(hub.publish(&joined("#room-1", "alice")).unwrap(), 0);
+        assert_eq!(hub.subscriber_count(), 1);
+    }
+}
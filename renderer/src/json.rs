@@ -0,0 +1,111 @@
+use crate::{Error, ErrorKind, PostProcessor};
+use serde::de::IgnoredAny;
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter, Serializer};
+use std::fmt::Write;
+
+/// Post processor for `json.j2` templates: rejects output that isn't valid JSON, and optionally
+/// reformats it. Keys keep the order they're rendered in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonOutput {
+    /// only check the output is valid JSON
+    #[default]
+    Validate,
+    /// re-serialize without whitespace
+    Compact,
+    /// re-serialize with two spaces of indentation
+    Pretty,
+}
+
+impl PostProcessor for JsonOutput {
+    fn matches(&self, _name: &str, mime: &str) -> bool {
+        mime.starts_with("application/json")
+    }
+
+    fn process(&self, name: &str, output: String) -> Result<String, Error> {
+        serde_json::from_str::<IgnoredAny>(&output).map_err(|e| invalid_json(name, &output, e))?;
+        match self {
+            JsonOutput::Validate => Ok(output),
+            JsonOutput::Compact => reformat(name, &output, CompactFormatter),
+            JsonOutput::Pretty => reformat(name, &output, PrettyFormatter::new()),
+        }
+    }
+}
+
+fn reformat(name: &str, output: &str, formatter: impl Formatter) -> Result<String, Error> {
+    let mut buf = Vec::with_capacity(output.len());
+    let mut de = serde_json::Deserializer::from_str(output);
+    let mut ser = Serializer::with_formatter(&mut buf, formatter);
+    serde_transcode::transcode(&mut de, &mut ser).map_err(|e| invalid_json(name, output, e))?;
+    // serde_json only writes valid utf-8
+    Ok(String::from_utf8(buf).expect("json should be valid utf-8"))
+}
+
+/// an error pointing at the line and column of the rendered output
+fn invalid_json(name: &str, output: &str, e: serde_json::Error) -> Error {
+    let mut detail = format!("template `{name}` rendered invalid JSON: {e}");
+    if let Some(line) = e.line().checked_sub(1).and_then(|n| output.lines().nth(n)) {
+        let _ = write!(
+            detail,
+            "\n  {line}\n  {:>width$}",
+            "^",
+            width = e.column().max(1)
+        );
+    }
+    Error::new(ErrorKind::BadSerialization, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Renderer;
+    use minijinja::context;
+
+    const TEMPLATE: &str = r#"{
+  "name": "{{ name }}",
+  "tags": [{% for tag in tags %}"{{ tag }}"{% if not loop.last %}, {% endif %}{% endfor %}],
+  "id": {{ id }}
+}"#;
+
+    #[test]
+    fn json_output_should_validate_and_reformat() {
+        let ctx = context!(name => "foo", tags => vec!["a", "b"], id => 1);
+        let ret = render(JsonOutput::Validate, &ctx).unwrap();
+        assert_eq!(
+            ret,
+            "{\n  \"name\": \"foo\",\n  \"tags\": [\"a\", \"b\"],\n  \"id\": 1\n}"
+        );
+        let ret = render(JsonOutput::Compact, &ctx).unwrap();
+        assert_eq!(ret, r#"{"name":"foo","tags":["a","b"],"id":1}"#);
+        let ret = render(JsonOutput::Pretty, &ctx).unwrap();
+        assert_eq!(
+            ret,
+            "{\n  \"name\": \"foo\",\n  \"tags\": [\n    \"a\",\n    \"b\"\n  ],\n  \"id\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn json_output_should_report_the_position_of_invalid_json() {
+        // `id` is undefined and renders as nothing
+        let ctx = context!(name => "foo", tags => vec!["a"]);
+        let err = render(JsonOutput::Compact, &ctx).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadSerialization);
+        assert_eq!(
+            err.detail().unwrap(),
+            "template `a.json.j2` rendered invalid JSON: expected value at line 5 column 1\n  }\n  ^"
+        );
+    }
+
+    #[test]
+    fn json_output_should_ignore_other_templates() {
+        let mut renderer = Renderer::default().with_post_processor(JsonOutput::Compact);
+        renderer.add_template("a.txt.j2", "{ not json").unwrap();
+        let ret = renderer.render_template("a.txt.j2", &context!()).unwrap();
+        assert_eq!(ret, "{ not json");
+    }
+
+    fn render(output: JsonOutput, ctx: &minijinja::Value) -> Result<String, Error> {
+        let mut renderer = Renderer::default().with_post_processor(output);
+        renderer.add_template("a.json.j2", TEMPLATE).unwrap();
+        renderer.render_template("a.json.j2", ctx)
+    }
+}
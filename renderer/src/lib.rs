@@ -1,55 +1,82 @@
+#[cfg(test)]
+mod fixtures;
 mod frameworks;
-
+#[cfg(feature = "hub")]
+mod hub;
+mod json;
+mod loader;
 #[cfg(feature = "minify")]
-use minify_html::{minify, Cfg};
+mod minify;
+mod oob;
+mod post_process;
+#[cfg(feature = "reload")]
+mod reload;
+mod rendered;
+mod sse;
+mod subscription;
+mod swap;
+
 use minijinja::Environment;
+use post_process::Pipeline;
 use serde::Serialize;
 use std::{
     borrow::Cow,
+    io,
     ops::{Deref, DerefMut},
 };
 
+#[doc(hidden)]
+pub mod __private;
 pub mod filters;
 
-pub use minijinja::Error;
+#[cfg(feature = "hub")]
+pub use hub::{EventHub, LagPolicy};
+pub use json::JsonOutput;
+pub use loader::DEFAULT_TEMPLATE_GLOB;
+#[cfg(feature = "minify")]
+pub use minify::MinifyConfig;
+pub use minijinja::{Error, ErrorKind};
+pub use post_process::PostProcessor;
+#[cfg(feature = "reload")]
+pub use reload::ReloadingRenderer;
+pub use rendered::{EventFormat, RenderedEvent};
+pub use sse::SseFrame;
+pub use subscription::Subscription;
+pub use swap::{Swap, SwapSpec};
 
 #[cfg(feature = "derive")]
 pub use derive_jinja_renderer::*;
 
-#[cfg(feature = "minify")]
-const CFG: Cfg = Cfg {
-    do_not_minify_doctype: true,
-    ensure_spec_compliant_unquoted_attribute_values: true,
-    keep_closing_tags: true,
-    keep_html_and_head_opening_tags: true,
-    keep_spaces_between_attributes: true,
-    keep_input_type_text_attr: true,
-    preserve_brace_template_syntax: true,
-    minify_css: false,
-    minify_js: false,
-    keep_comments: false,
-    keep_ssi_comments: false,
-    preserve_chevron_percent_template_syntax: false,
-    remove_bangs: false,
-    remove_processing_instructions: false,
-};
-
 pub trait RenderContext: Serialize {
     /// The name of the template to render
     const TEMPLATE_NAME: &'static str;
+    /// The inline source of the template, registered by `Renderer::add_inline_template`
+    const TEMPLATE_SOURCE: Option<&'static str> = None;
     /// The MIME type (Content-Type) of the data that gets rendered by this Template
     const MIME_TYPE: &'static str;
+    /// The block to render instead of the full template for htmx requests
+    const PARTIAL_BLOCK: Option<&'static str> = None;
+    /// Only render the partial block when the htmx request targets this element id
+    const PARTIAL_TARGET: Option<&'static str> = None;
     /// render the context data
     fn render(&self, renderer: &Renderer) -> Result<String, Error>;
+    /// render a single block of the template with the context data
+    fn render_block(&self, renderer: &Renderer, block: &str) -> Result<String, Error> {
+        renderer.render_block(Self::TEMPLATE_NAME, block, self)
+    }
+    /// render the context data into the writer as it's produced
+    fn render_to_writer<W: io::Write>(&self, renderer: &Renderer, writer: W) -> Result<(), Error> {
+        renderer.render_to_writer(Self::TEMPLATE_NAME, self, writer)
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EventInfo {
     pub name: &'static str,
-    pub receivers: &'static [&'static str],
+    pub receivers: Vec<Cow<'static, str>>,
     pub target: Cow<'static, str>,
-    pub swap: &'static str,
+    pub swap: SwapSpec,
     pub id_field: &'static str,
 }
 
@@ -60,6 +87,32 @@ pub trait RenderEvent {
     fn render_event_data(&self, renderer: &Renderer) -> Result<String, Error>;
     // event id
     fn event_info(&self) -> EventInfo;
+    /// render the event as a `text/event-stream` frame, set `id` or `retry` on it if needed
+    fn to_sse_frame(&self, renderer: &Renderer) -> Result<SseFrame, Error> {
+        // `EVENT_NAME` of an enum of events is empty, the variant knows the name
+        Ok(SseFrame::new(
+            self.event_info().name,
+            self.render_event_data(renderer)?,
+        ))
+    }
+    /// render the event once, for transports to encode it as they need. The default splits
+    /// `render_event_data`, the derived events serialize their data directly.
+    fn render_event(&self, renderer: &Renderer) -> Result<RenderedEvent, Error> {
+        let data = self.render_event_data(renderer)?;
+        RenderedEvent::from_lines(self.event_info(), &data)
+    }
+    /// the templated HTML of the event, `None` if it has no template
+    fn render_html(&self, _renderer: &Renderer) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+    /// render the templated HTML as an htmx out-of-band swap of the target, so it can be pushed
+    /// over the htmx SSE or WebSocket extensions as is. `None` if the event has no template.
+    fn render_oob(&self, renderer: &Renderer) -> Result<Option<String>, Error> {
+        match self.render_html(renderer)? {
+            Some(html) => oob::wrap(&self.event_info(), &html).map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 pub struct OwnedTemplate {
@@ -67,20 +120,29 @@ pub struct OwnedTemplate {
     pub data: Cow<'static, str>,
 }
 
-#[derive(Debug)]
-pub struct Renderer(Environment<'static>);
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    env: Environment<'static>,
+    #[cfg(feature = "minify")]
+    minify: MinifyConfig,
+    // sources of the templates minified when they were added, to minify them again with a new
+    // config
+    #[cfg(feature = "minify")]
+    preminified: std::collections::HashMap<String, Cow<'static, str>>,
+    pipeline: Pipeline,
+}
 
 impl Deref for Renderer {
     type Target = Environment<'static>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.env
     }
 }
 
 impl DerefMut for Renderer {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.env
     }
 }
 
@@ -101,7 +163,24 @@ impl OwnedTemplate {
 
 impl Renderer {
     pub fn new(env: Environment<'static>) -> Self {
-        Self(env)
+        Self {
+            env,
+            #[cfg(feature = "minify")]
+            minify: Default::default(),
+            #[cfg(feature = "minify")]
+            preminified: Default::default(),
+            pipeline: Default::default(),
+        }
+    }
+
+    /// register the inline template of `T` (`#[template(source = "...")]`) under
+    /// `T::TEMPLATE_NAME`, so it's rendered, included or extended like any other template. Does
+    /// nothing for a template loaded by name.
+    pub fn add_inline_template<T: RenderContext>(&mut self) -> Result<(), Error> {
+        match T::TEMPLATE_SOURCE {
+            Some(source) => self.add_source(T::TEMPLATE_NAME, source),
+            None => Ok(()),
+        }
     }
 
     pub fn add_templates(
@@ -109,42 +188,68 @@ impl Renderer {
         templates: impl Iterator<Item = OwnedTemplate>,
     ) -> Result<(), Error> {
         for tpl in templates {
-            self.add_template_owned(tpl.name, tpl.data)?;
+            self.add_source(tpl.name, tpl.data)?;
         }
         Ok(())
     }
 
     pub fn render_template<T: Serialize>(&self, name: &str, context: &T) -> Result<String, Error> {
-        let tpl = self.0.get_template(name)?;
-        let mime = if name.ends_with("html.j2") {
-            "text/html; charset=utf-8"
-        } else if name.ends_with("json.j2") {
-            "application/json; charset=utf-8"
-        } else {
-            "text/plain; charset=utf-8"
-        };
-        self.render_minified(tpl, mime, context)
-    }
-
-    fn render_minified(
+        let tpl = self.env.get_template(name)?;
+        let ret = tpl.render(context)?;
+        self.post_process(name, ret)
+    }
+
+    /// render a single `{% block %}` of the template, e.g. for htmx partial updates
+    pub fn render_block<T: Serialize + ?Sized>(
         &self,
-        tpl: minijinja::Template<'_, '_>,
-        #[allow(unused_variables)] mime: &str,
-        context: &impl Serialize,
+        name: &str,
+        block: &str,
+        context: &T,
     ) -> Result<String, Error> {
+        let tpl = self.env.get_template(name)?;
+        let ret = tpl.eval_to_state(context)?.render_block(block)?;
+        self.post_process(name, ret)
+    }
+
+    /// render the template into `writer`. The output is streamed as it's rendered, unless a post
+    /// processor applies to the template: then it's written at once after being processed. Use
+    /// `MinifyConfig::minify_on_load` to stream minified HTML.
+    pub fn render_to_writer<T: Serialize + ?Sized, W: io::Write>(
+        &self,
+        name: &str,
+        context: &T,
+        mut writer: W,
+    ) -> Result<(), Error> {
+        let tpl = self.env.get_template(name)?;
+        if !self.has_post_processors(name) {
+            tpl.render_to_write(context, writer)?;
+            return Ok(());
+        }
         #[cfg(feature = "minify")]
-        {
-            let ret = tpl.render(context)?;
-            if mime.starts_with("text/html") {
-                let minified = minify(ret.as_bytes(), &CFG);
-                Ok(unsafe { String::from_utf8_unchecked(minified) })
-            } else {
-                Ok(ret)
-            }
+        if self.should_minify(name, post_process::mime_type(name)) {
+            minify::warn_buffered(name);
         }
-        #[cfg(not(feature = "minify"))]
-        {
-            tpl.render(context)
+        let ret = self.post_process(name, tpl.render(context)?)?;
+        writer.write_all(ret.as_bytes()).map_err(|e| {
+            Error::new(ErrorKind::WriteFailure, "failed to write rendered output").with_source(e)
+        })
+    }
+
+    /// add a template the renderer loaded itself, minifying its source if configured to
+    pub(crate) fn add_source(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        source: impl Into<Cow<'static, str>>,
+    ) -> Result<(), Error> {
+        let (name, source) = (name.into(), source.into());
+        #[cfg(feature = "minify")]
+        if let Some(minified) = self.minify.preminify(&name, &source) {
+            self.add_template_owned(name.clone(), minified)?;
+            self.preminified.insert(name.into_owned(), source);
+            return Ok(());
         }
+        #[cfg(feature = "minify")]
+        self.preminified.remove(name.as_ref());
+        self.add_template_owned(name, source)
     }
 }
@@ -0,0 +1,160 @@
+use crate::{Error, Renderer};
+use glob::{MatchOptions, Pattern};
+use minijinja::ErrorKind;
+use std::{fmt::Write, fs, path::Path};
+use walkdir::WalkDir;
+
+/// default glob used by [`Renderer::from_dir`]
+pub const DEFAULT_TEMPLATE_GLOB: &str = "**/*.j2";
+
+pub(crate) const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+impl Renderer {
+    /// create a renderer with every `*.j2` file under `path` registered
+    pub fn from_dir(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut renderer = Self::default();
+        renderer.load_dir(path, DEFAULT_TEMPLATE_GLOB)?;
+        Ok(renderer)
+    }
+
+    /// register every file under `path` matching `pattern`, named by its path relative to `path`
+    /// (e.g. `pages/home.html.j2`). All failures are reported together in a single error.
+    pub fn load_dir(&mut self, path: impl AsRef<Path>, pattern: &str) -> Result<(), Error> {
+        let path = path.as_ref();
+        let pattern = parse_glob(pattern)?;
+
+        let mut failures = Vec::new();
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let name = e.path().unwrap_or(path).display().to_string();
+                    failures.push((name, e.to_string()));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(name) = template_name(path, entry.path()) else {
+                continue;
+            };
+            if !pattern.matches_with(&name, MATCH_OPTIONS) {
+                continue;
+            }
+
+            match fs::read_to_string(entry.path()) {
+                Ok(data) => {
+                    if let Err(e) = self.add_source(name.clone(), data) {
+                        failures.push((name, e.to_string()));
+                    }
+                }
+                Err(e) => failures.push((name, e.to_string())),
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        let mut detail = format!(
+            "failed to load {} template(s) from {}:",
+            failures.len(),
+            path.display()
+        );
+        for (name, e) in failures {
+            let _ = write!(detail, "\n  {name}: {e}");
+        }
+        Err(Error::new(ErrorKind::InvalidOperation, detail))
+    }
+}
+
+pub(crate) fn parse_glob(pattern: &str) -> Result<Pattern, Error> {
+    Pattern::new(pattern).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("invalid template glob {pattern:?}: {e}"),
+        )
+    })
+}
+
+/// template name of `file` relative to `root`, always separated by `/`
+pub(crate) fn template_name(root: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root).ok()?;
+    let parts = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn load_dir_should_register_templates_by_relative_path() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "index.html.j2", "{{ name }}");
+        write(dir.path(), "pages/home.html.j2", "home {{ name }}");
+        write(dir.path(), "pages/readme.md", "ignored");
+
+        let renderer = Renderer::from_dir(dir.path()).unwrap();
+
+        let ret = renderer
+            .render_template("pages/home.html.j2", &context())
+            .unwrap();
+        assert_eq!(ret, "home world");
+        assert!(renderer.get_template("index.html.j2").is_ok());
+        assert!(renderer.get_template("pages/readme.md").is_err());
+    }
+
+    #[test]
+    fn load_dir_should_respect_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "index.html.j2", "{{ name }}");
+        write(dir.path(), "pages/home.html.j2", "home {{ name }}");
+
+        let mut renderer = Renderer::default();
+        renderer.load_dir(dir.path(), "pages/*.j2").unwrap();
+
+        assert!(renderer.get_template("pages/home.html.j2").is_ok());
+        assert!(renderer.get_template("index.html.j2").is_err());
+    }
+
+    #[test]
+    fn load_dir_should_report_all_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.html.j2", "{{ name ");
+        write(dir.path(), "b.html.j2", "{{ name }}");
+        write(dir.path(), "c/d.html.j2", "{% if %}");
+
+        let mut renderer = Renderer::default();
+        let err = renderer
+            .load_dir(dir.path(), DEFAULT_TEMPLATE_GLOB)
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+        let msg = err.to_string();
+        assert!(msg.contains("failed to load 2 template(s)"), "{msg}");
+        assert!(msg.contains("a.html.j2: syntax error"), "{msg}");
+        assert!(msg.contains("c/d.html.j2: syntax error"), "{msg}");
+        // valid templates are still registered
+        assert!(renderer.get_template("b.html.j2").is_ok());
+    }
+
+    fn write(root: &Path, name: &str, content: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn context() -> minijinja::Value {
+        minijinja::context! { name => "world" }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    loader::{parse_glob, MATCH_OPTIONS},
+    post_process::mime_type,
+    Error, Renderer,
+};
+use glob::Pattern;
+use minify_html::{minify, Cfg};
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+use tracing::warn;
+
+const CFG: Cfg = Cfg {
+    do_not_minify_doctype: true,
+    ensure_spec_compliant_unquoted_attribute_values: true,
+    keep_closing_tags: true,
+    keep_html_and_head_opening_tags: true,
+    keep_spaces_between_attributes: true,
+    keep_input_type_text_attr: true,
+    preserve_brace_template_syntax: true,
+    minify_css: false,
+    minify_js: false,
+    keep_comments: false,
+    keep_ssi_comments: false,
+    preserve_chevron_percent_template_syntax: false,
+    remove_bangs: false,
+    remove_processing_instructions: false,
+};
+
+/// How rendered HTML gets minified. The default keeps CSS and JS untouched and drops comments.
+#[derive(Clone)]
+pub struct MinifyConfig {
+    cfg: Cfg,
+    exclude: Vec<Pattern>,
+    on_load: bool,
+    // starts the sources minified with this config, set when the renderer takes it
+    marker: String,
+}
+
+impl Default for MinifyConfig {
+    fn default() -> Self {
+        Self {
+            cfg: CFG,
+            exclude: Vec::new(),
+            on_load: false,
+            marker: String::new(),
+        }
+    }
+}
+
+impl From<Cfg> for MinifyConfig {
+    fn from(cfg: Cfg) -> Self {
+        Self {
+            cfg,
+            exclude: Vec::new(),
+            on_load: false,
+            marker: String::new(),
+        }
+    }
+}
+
+impl fmt::Debug for MinifyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MinifyConfig")
+            .field("minify_css", &self.cfg.minify_css)
+            .field("minify_js", &self.cfg.minify_js)
+            .field("keep_comments", &self.cfg.keep_comments)
+            .field("keep_ssi_comments", &self.cfg.keep_ssi_comments)
+            .field("exclude", &self.exclude)
+            .field("on_load", &self.on_load)
+            .finish()
+    }
+}
+
+impl MinifyConfig {
+    /// minify CSS in `<style>` tags and `style` attributes
+    pub fn minify_css(mut self, yes: bool) -> Self {
+        self.cfg.minify_css = yes;
+        self
+    }
+
+    /// minify JS in `<script>` tags
+    pub fn minify_js(mut self, yes: bool) -> Self {
+        self.cfg.minify_js = yes;
+        self
+    }
+
+    /// keep all HTML comments
+    pub fn keep_comments(mut self, yes: bool) -> Self {
+        self.cfg.keep_comments = yes;
+        self
+    }
+
+    /// keep SSI comments (`<!--# ... -->`)
+    pub fn keep_ssi_comments(mut self, yes: bool) -> Self {
+        self.cfg.keep_ssi_comments = yes;
+        self
+    }
+
+    /// don't minify templates whose name matches the glob, e.g. `emails/**`
+    pub fn exclude(mut self, pattern: &str) -> Result<Self, Error> {
+        self.exclude.push(parse_glob(pattern)?);
+        Ok(self)
+    }
+
+    /// minify the template source when it's added by `add_templates`, `load_dir` or a
+    /// reload, instead of the output of every render, so rendering does no extra pass. The
+    /// source keeps its jinja syntax, which minify-html can't see through: templated attribute
+    /// values keep their quotes and whitespace next to jinja tags is collapsed to a space instead
+    /// of dropped, minifying the output would only remove or rewrite those. Templates with jinja
+    /// in code `minify_js` or `minify_css` would minify, and templates registered with
+    /// `add_template`, are still minified on every render.
+    pub fn minify_on_load(mut self, yes: bool) -> Self {
+        self.on_load = yes;
+        self
+    }
+
+    /// the minified source if the template should be minified on load
+    pub(crate) fn preminify(&self, name: &str, source: &str) -> Option<String> {
+        if !self.on_load || !self.applies(name, mime_type(name)) || self.has_templated_code(source)
+        {
+            return None;
+        }
+        let mut cfg = self.cfg.clone();
+        cfg.preserve_brace_template_syntax = true;
+        Some(format!("{}{}", self.marker, run(&cfg, source)))
+    }
+
+    /// whether the source has jinja in JS or CSS that would be minified. Minifying it could
+    /// change what gets templated in, e.g. turn a string into a JS template literal.
+    fn has_templated_code(&self, source: &str) -> bool {
+        let source = source.to_ascii_lowercase();
+        let templated = |open: &str, close: &str| {
+            source.match_indices(open).any(|(i, _)| {
+                let code = &source[i + open.len()..];
+                let code = &code[..code.find(close).unwrap_or(code.len())];
+                code.contains("{{") || code.contains("{%")
+            })
+        };
+        (self.cfg.minify_js && templated("<script", "</script"))
+            || (self.cfg.minify_css
+                && (templated("<style", "</style")
+                    || templated("style=\"", "\"")
+                    || templated("style='", "'")))
+    }
+
+    /// minify the rendered output
+    pub(crate) fn process(&self, output: &str) -> String {
+        run(&self.cfg, output)
+    }
+
+    fn is_preminified(&self, source: &str) -> bool {
+        self.on_load && source.starts_with(&self.marker)
+    }
+
+    /// whether the output of template `name` is HTML to minify
+    pub(crate) fn applies(&self, name: &str, mime: &str) -> bool {
+        mime.starts_with("text/html")
+            && !self
+                .exclude
+                .iter()
+                .any(|p| p.matches_with(name, MATCH_OPTIONS))
+    }
+}
+
+/// warn once that minifying the output of template `name` keeps it from being streamed
+pub(crate) fn warn_buffered(name: &str) {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        warn!(
+            "template {name} is minified once rendered, so it's written at once instead of \
+             streamed; minify it on load to stream it"
+        );
+    }
+}
+
+fn run(cfg: &Cfg, input: &str) -> String {
+    let minified = minify(input.as_bytes(), cfg);
+    // minify-html only removes or rewrites ASCII, so the output stays valid utf-8
+    unsafe { String::from_utf8_unchecked(minified) }
+}
+
+impl Renderer {
+    /// use `config` to minify the rendered HTML
+    pub fn with_minify_config(mut self, config: MinifyConfig) -> Self {
+        self.set_minify_config(config);
+        self
+    }
+
+    /// use `config` to minify the rendered HTML. Templates minified on load with the previous
+    /// config are minified again from their source.
+    pub fn set_minify_config(&mut self, mut config: MinifyConfig) {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        config.marker = format!("{{#minified:{}#}}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+        let previous = std::mem::replace(&mut self.minify, config);
+        for (name, source) in std::mem::take(&mut self.preminified) {
+            // a template registered again through the env isn't ours to minify
+            let current = self.env.get_template(&name);
+            let unchanged = current
+                .is_ok_and(|tpl| previous.is_preminified(tpl.source()) || tpl.source() == source);
+            if !unchanged {
+                continue;
+            }
+            if let Err(e) = self.add_source(name.clone(), source.clone()) {
+                warn!("failed to minify template {name} again: {e}");
+            }
+            // kept to be minified on load by a later config
+            self.preminified.entry(name).or_insert(source);
+        }
+    }
+
+    /// whether the source of template `name` was minified when it was added
+    pub(crate) fn is_preminified(&self, name: &str) -> bool {
+        self.minify.on_load
+            && self
+                .get_template(name)
+                .is_ok_and(|tpl| self.minify.is_preminified(tpl.source()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+
+    const TEMPLATE: &str = "<div>\n  <!--# include file=\"x\" -->\n  <!-- note -->\n  <style>p { color: red; }</style>\n</div>";
+
+    #[test]
+    fn default_config_should_keep_css_and_drop_comments() {
+        let renderer = renderer(MinifyConfig::default());
+        let ret = renderer.render_template("a.html.j2", &context!()).unwrap();
+        assert_eq!(ret, "<div><style>p { color: red; }</style></div>");
+    }
+
+    #[test]
+    fn custom_config_should_be_used() {
+        let config = MinifyConfig::default()
+            .minify_css(true)
+            .keep_ssi_comments(true);
+        let renderer = renderer(config);
+        let ret = renderer.render_template("a.html.j2", &context!()).unwrap();
+        assert_eq!(
+            ret,
+            "<div><!--# include file=\"x\" --><style>p{color:red}</style></div>"
+        );
+    }
+
+    #[test]
+    fn excluded_templates_should_not_be_minified() {
+        let config = MinifyConfig::default().exclude("emails/*").unwrap();
+        let renderer = renderer(config);
+        let ret = renderer
+            .render_template("emails/a.html.j2", &context!())
+            .unwrap();
+        assert_eq!(ret, TEMPLATE);
+        let ret = renderer.render_template("a.html.j2", &context!()).unwrap();
+        assert_eq!(ret, "<div><style>p { color: red; }</style></div>");
+    }
+
+    fn renderer(config: MinifyConfig) -> Renderer {
+        let mut renderer = Renderer::default().with_minify_config(config);
+        renderer.add_template("a.html.j2", TEMPLATE).unwrap();
+        renderer.add_template("emails/a.html.j2", TEMPLATE).unwrap();
+        renderer
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{Error, ErrorKind, EventInfo, Swap};
+use minijinja::HtmlEscape;
+
+/// mark `html` as an htmx out-of-band swap: `hx-swap-oob="{swap}:{target}"`. Swap styles other
+/// than `outerHTML` swap in the content of the oob element, a `<template>` so table rows or list
+/// items are parsed as they are. `outerHTML` swaps in the element itself, so the attribute goes on
+/// the root element of `html`, which should keep the id of the target to be found again. Out of
+/// band swaps take no modifiers, only the style is used.
+pub(crate) fn wrap(info: &EventInfo, html: &str) -> Result<String, Error> {
+    let oob = format!("{}:{}", info.swap.style, info.target);
+    let attr = format!(r#" hx-swap-oob="{}""#, HtmlEscape(&oob));
+    if info.swap.style != Swap::OuterHtml {
+        return Ok(format!("<template{attr}>{html}</template>"));
+    }
+
+    let start = html.len() - html.trim_start().len();
+    let name_len = html[start..]
+        .strip_prefix('<')
+        .filter(|tag| tag.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .map(|tag| {
+            tag.find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                .unwrap_or(tag.len())
+        });
+    let Some(name_len) = name_len else {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!(
+                "the outerHTML out of band swap of event {} needs HTML starting with an element",
+                info.name
+            ),
+        ));
+    };
+    let (tag, rest) = html.split_at(start + 1 + name_len);
+    Ok(format!("{tag}{attr}{rest}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fixtures::{info, TestEvent},
+        ErrorKind, RenderEvent, Renderer, Swap, SwapSpec,
+    };
+
+    fn message(target: &'static str) -> TestEvent {
+        let mut event = TestEvent::new(info("message", &["#list"]), "hello");
+        event.info.target = target.into();
+        event.info.swap = SwapSpec::new(Swap::BeforeEnd, "scroll:bottom");
+        event
+    }
+
+    #[test]
+    fn render_oob_should_wrap_html_in_a_template() {
+        let renderer = Renderer::default();
+        let event = message("#id-42");
+        assert_eq!(
+            event.render_oob(&renderer).unwrap().unwrap(),
+            r#"<template hx-swap-oob="beforeend:#id-42"><li>hello</li></template>"#
+        );
+
+        let event = message(r#".list[data-x="a"]"#);
+        assert_eq!(
+            event.render_oob(&renderer).unwrap().unwrap(),
+            r#"<template hx-swap-oob="beforeend:.list[data-x=&quot;a&quot;]"><li>hello</li></template>"#
+        );
+    }
+
+    #[test]
+    fn render_oob_should_keep_table_rows() {
+        let mut event = message("#rows");
+        event.html = Some("<tr><td>hello</td></tr>".into());
+        assert_eq!(
+            event.render_oob(&Renderer::default()).unwrap().unwrap(),
+            r#"<template hx-swap-oob="beforeend:#rows"><tr><td>hello</td></tr></template>"#
+        );
+    }
+
+    #[test]
+    fn render_oob_of_outer_html_should_mark_the_root_element() {
+        let mut event = message("#list");
+        event.info.swap = Swap::OuterHtml.into();
+        event.html = Some("\n<ul id=\"list\"><li>hello</li></ul>".into());
+        assert_eq!(
+            event.render_oob(&Renderer::default()).unwrap().unwrap(),
+            "\n<ul hx-swap-oob=\"outerHTML:#list\" id=\"list\"><li>hello</li></ul>"
+        );
+
+        event.html = Some("hello".into());
+        let err = event.render_oob(&Renderer::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+    }
+
+    #[test]
+    fn render_oob_without_template_should_be_none() {
+        let mut event = message("#list");
+        event.html = None;
+        assert!(event.render_oob(&Renderer::default()).unwrap().is_none());
+    }
+}
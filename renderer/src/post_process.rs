@@ -0,0 +1,150 @@
+use crate::{Error, Renderer};
+use std::{fmt, sync::Arc};
+
+/// A step that runs on the output of every matching template, after it's rendered.
+pub trait PostProcessor: Send + Sync {
+    /// whether the processor applies to the template, `mime` is derived from the template name
+    fn matches(&self, _name: &str, _mime: &str) -> bool {
+        true
+    }
+
+    fn process(&self, name: &str, output: String) -> Result<String, Error>;
+}
+
+/// a closure applies to every template
+impl<F> PostProcessor for F
+where
+    F: Fn(&str, String) -> Result<String, Error> + Send + Sync,
+{
+    fn process(&self, name: &str, output: String) -> Result<String, Error> {
+        self(name, output)
+    }
+}
+
+/// post processors in the order they're registered
+#[derive(Clone, Default)]
+pub(crate) struct Pipeline(Vec<Arc<dyn PostProcessor>>);
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pipeline({} processors)", self.0.len())
+    }
+}
+
+impl Renderer {
+    /// add a step to the post processing pipeline. Steps run in the order they're added, after
+    /// the built-in HTML minification.
+    pub fn add_post_processor(&mut self, processor: impl PostProcessor + 'static) {
+        self.pipeline.0.push(Arc::new(processor));
+    }
+
+    pub fn with_post_processor(mut self, processor: impl PostProcessor + 'static) -> Self {
+        self.add_post_processor(processor);
+        self
+    }
+
+    /// run the pipeline on the output rendered from template `name`
+    pub fn post_process(&self, name: &str, mut output: String) -> Result<String, Error> {
+        let mime = mime_type(name);
+
+        #[cfg(feature = "minify")]
+        if self.should_minify(name, mime) {
+            output = self.minify.process(&output);
+        }
+
+        for processor in self.pipeline.0.iter().filter(|p| p.matches(name, mime)) {
+            output = processor.process(name, output)?;
+        }
+        Ok(output)
+    }
+
+    /// whether the output of template `name` has to be processed after it's rendered
+    pub(crate) fn has_post_processors(&self, name: &str) -> bool {
+        let mime = mime_type(name);
+        #[cfg(feature = "minify")]
+        if self.should_minify(name, mime) {
+            return true;
+        }
+        self.pipeline.0.iter().any(|p| p.matches(name, mime))
+    }
+
+    #[cfg(feature = "minify")]
+    pub(crate) fn should_minify(&self, name: &str, mime: &str) -> bool {
+        self.minify.applies(name, mime) && !self.is_preminified(name)
+    }
+}
+
+/// the MIME type of the output of template `name`
+pub(crate) fn mime_type(name: &str) -> &'static str {
+    if name.ends_with("html.j2") {
+        "text/html; charset=utf-8"
+    } else if name.ends_with("json.j2") {
+        "application/json; charset=utf-8"
+    } else {
+        "text/plain; charset=utf-8"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+    use minijinja::context;
+
+    struct Nonce;
+
+    impl PostProcessor for Nonce {
+        fn matches(&self, _name: &str, mime: &str) -> bool {
+            mime.starts_with("text/html")
+        }
+
+        fn process(&self, _name: &str, output: String) -> Result<String, Error> {
+            Ok(output.replace("<script>", r#"<script nonce="abc">"#))
+        }
+    }
+
+    #[test]
+    fn post_processors_should_run_in_order_on_matching_templates() {
+        let mut renderer = Renderer::default()
+            .with_post_processor(Nonce)
+            .with_post_processor(|_: &str, output: String| Ok(output.trim().to_string()));
+        renderer
+            .add_template("a.html.j2", " <script>{{ v }}</script> ")
+            .unwrap();
+        renderer
+            .add_template("a.txt.j2", " <script>{{ v }}</script> ")
+            .unwrap();
+
+        let ret = renderer
+            .render_template("a.html.j2", &context!(v => 1))
+            .unwrap();
+        assert_eq!(ret, r#"<script nonce="abc">1</script>"#);
+        let ret = renderer
+            .render_template("a.txt.j2", &context!(v => 1))
+            .unwrap();
+        assert_eq!(ret, "<script>1</script>");
+    }
+
+    #[test]
+    fn post_processor_errors_should_fail_the_render() {
+        let mut renderer = Renderer::default().with_post_processor(|name: &str, _: String| {
+            Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("bad {name}"),
+            ))
+        });
+        renderer.add_template("a.txt.j2", "hello").unwrap();
+        let err = renderer
+            .render_template("a.txt.j2", &context!())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+        assert_eq!(err.detail(), Some("bad a.txt.j2"));
+    }
+
+    #[test]
+    fn mime_type_should_follow_the_template_name() {
+        assert_eq!(mime_type("a.html.j2"), "text/html; charset=utf-8");
+        assert_eq!(mime_type("a.json.j2"), "application/json; charset=utf-8");
+        assert_eq!(mime_type("a.j2"), "text/plain; charset=utf-8");
+    }
+}
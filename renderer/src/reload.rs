@@ -0,0 +1,266 @@
+use crate::{
+    loader::{parse_glob, template_name, MATCH_OPTIONS},
+    Error, Renderer,
+};
+use glob::Pattern;
+use minijinja::ErrorKind;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// A [`Renderer`] that follows changes of a template directory. Every change is applied to a
+/// copy of the current renderer which then replaces it atomically, so a render never sees a
+/// half-updated set of templates.
+pub struct ReloadingRenderer {
+    shared: Arc<Shared>,
+    _watcher: RecommendedWatcher,
+}
+
+struct Shared {
+    root: PathBuf,
+    pattern: Pattern,
+    current: RwLock<Arc<Renderer>>,
+    // names of the templates loaded from `root`. It also serializes updates so that concurrent
+    // reloads don't drop each other's changes.
+    names: Mutex<BTreeSet<String>>,
+}
+
+impl Renderer {
+    /// load the templates under `path` matching `pattern` and keep them in sync with the disk
+    pub fn watch_dir(
+        mut self,
+        path: impl AsRef<Path>,
+        pattern: &str,
+    ) -> Result<ReloadingRenderer, Error> {
+        let root = fs::canonicalize(path.as_ref()).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                format!("cannot watch {}: {e}", path.as_ref().display()),
+            )
+        })?;
+        let pattern = parse_glob(pattern)?;
+        self.load_dir(&root, pattern.as_str())?;
+        let names = template_files(&root)
+            .filter_map(|path| template_name(&root, &path))
+            .filter(|name| pattern.matches_with(name, MATCH_OPTIONS))
+            .collect();
+
+        let shared = Arc::new(Shared {
+            root,
+            pattern,
+            current: RwLock::new(Arc::new(self)),
+            names: Mutex::new(names),
+        });
+
+        let handler = {
+            let shared = shared.clone();
+            move |event: notify::Result<Event>| match event {
+                Ok(event) if !event.kind.is_access() => shared.apply(&event.paths),
+                Ok(_) => {}
+                Err(e) => warn!("failed to watch templates: {}", e),
+            }
+        };
+        let mut watcher = notify::recommended_watcher(handler).map_err(watch_error)?;
+        watcher
+            .watch(&shared.root, RecursiveMode::Recursive)
+            .map_err(watch_error)?;
+
+        Ok(ReloadingRenderer {
+            shared,
+            _watcher: watcher,
+        })
+    }
+}
+
+impl ReloadingRenderer {
+    /// a snapshot of the current templates, use it for the whole request
+    pub fn load(&self) -> Arc<Renderer> {
+        self.shared.current.read().unwrap().clone()
+    }
+}
+
+impl Shared {
+    fn apply(&self, paths: &[PathBuf]) {
+        let mut names = self.names.lock().unwrap();
+        let paths = paths.iter().flat_map(|path| {
+            if path.is_dir() {
+                // files created together with a new directory may be written before it's watched
+                template_files(path).collect()
+            } else if path.exists() {
+                vec![path.clone()]
+            } else {
+                // a removed or moved directory takes its templates with it
+                let dir = template_name(&self.root, path).map(|name| format!("{name}/"));
+                let removed = names
+                    .iter()
+                    .filter(|name| dir.as_ref().is_some_and(|dir| name.starts_with(dir)))
+                    .map(|name| self.root.join(name));
+                std::iter::once(path.clone()).chain(removed).collect()
+            }
+        });
+        let changes = paths
+            .filter_map(|path| {
+                let name = template_name(&self.root, &path)?;
+                self.pattern
+                    .matches_with(&name, MATCH_OPTIONS)
+                    .then_some((name, path))
+            })
+            .collect::<Vec<_>>();
+        if changes.is_empty() {
+            return;
+        }
+
+        let mut next = Renderer::clone(&self.current.read().unwrap());
+        for (name, path) in changes {
+            if !path.exists() {
+                info!("template {} removed", name);
+                next.remove_template(&name);
+                names.remove(&name);
+                continue;
+            }
+            // templates that fail to load keep their previous version
+            let ret = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| {
+                    next.add_source(name.clone(), data)
+                        .map_err(|e| e.to_string())
+                });
+            match ret {
+                Ok(()) => {
+                    info!("template {} reloaded", name);
+                    names.insert(name);
+                }
+                Err(e) => warn!("failed to reload template {}: {}", name, e),
+            }
+        }
+        *self.current.write().unwrap() = Arc::new(next);
+    }
+}
+
+fn template_files(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+}
+
+fn watch_error(e: notify::Error) -> Error {
+    Error::new(
+        ErrorKind::InvalidOperation,
+        format!("failed to watch templates: {e}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn reloading_renderer_should_follow_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.html.j2"), "v1 {{ name }}").unwrap();
+
+        let renderer = Renderer::default()
+            .watch_dir(dir.path(), crate::DEFAULT_TEMPLATE_GLOB)
+            .unwrap();
+        let snapshot = renderer.load();
+        assert_eq!(render(&snapshot, "a.html.j2").unwrap(), "v1 world");
+
+        fs::write(dir.path().join("a.html.j2"), "v2 {{ name }}").unwrap();
+        fs::create_dir(dir.path().join("pages")).unwrap();
+        fs::write(dir.path().join("pages/b.html.j2"), "b {{ name }}").unwrap();
+        wait_until(|| {
+            let current = renderer.load();
+            render(&current, "a.html.j2").as_deref() == Ok("v2 world")
+                && render(&current, "pages/b.html.j2").as_deref() == Ok("b world")
+        });
+        // an existing snapshot is never modified
+        assert_eq!(render(&snapshot, "a.html.j2").unwrap(), "v1 world");
+
+        // a broken template keeps its previous version. Save it the way editors do, a plain
+        // write would first truncate the file and make us load an empty template.
+        let tmp = dir.path().join("a.html.j2.tmp");
+        fs::write(&tmp, "{{ name").unwrap();
+        fs::rename(tmp, dir.path().join("a.html.j2")).unwrap();
+        fs::remove_file(dir.path().join("pages/b.html.j2")).unwrap();
+        wait_until(|| render(&renderer.load(), "pages/b.html.j2").is_err());
+        assert_eq!(render(&renderer.load(), "a.html.j2").unwrap(), "v2 world");
+    }
+
+    #[test]
+    fn reloading_renderer_should_follow_moved_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("pages/nested")).unwrap();
+        fs::write(dir.path().join("pages/a.html.j2"), "a {{ name }}").unwrap();
+        fs::write(dir.path().join("pages/nested/b.html.j2"), "b {{ name }}").unwrap();
+        fs::write(dir.path().join("pages.html.j2"), "page {{ name }}").unwrap();
+
+        let renderer = Renderer::default()
+            .watch_dir(dir.path(), crate::DEFAULT_TEMPLATE_GLOB)
+            .unwrap();
+        assert_eq!(
+            render(&renderer.load(), "pages/a.html.j2").unwrap(),
+            "a world"
+        );
+
+        fs::rename(dir.path().join("pages"), dir.path().join("pages2")).unwrap();
+        wait_until(|| {
+            let current = render_all(&renderer.load());
+            current[..2].iter().all(Result::is_err)
+                && current[2..] == [Ok("a world".into()), Ok("b world".into())]
+        });
+        // only the templates under the moved directory are removed
+        assert_eq!(
+            render(&renderer.load(), "pages.html.j2").unwrap(),
+            "page world"
+        );
+
+        fs::rename(dir.path().join("pages2"), dir.path().join("pages")).unwrap();
+        wait_until(|| {
+            let current = render_all(&renderer.load());
+            current[..2] == [Ok("a world".into()), Ok("b world".into())]
+                && current[2..].iter().all(Result::is_err)
+        });
+    }
+
+    fn render_all(renderer: &Renderer) -> Vec<Result<String, String>> {
+        [
+            "pages/a.html.j2",
+            "pages/nested/b.html.j2",
+            "pages2/a.html.j2",
+            "pages2/nested/b.html.j2",
+        ]
+        .into_iter()
+        .map(|name| render(renderer, name))
+        .collect()
+    }
+
+    fn render(renderer: &Renderer, name: &str) -> Result<String, String> {
+        renderer
+            .render_template(name, &context! { name => "world" })
+            .map_err(|e| e.to_string())
+    }
+
+    fn wait_until(f: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !f() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "templates not reloaded in time"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
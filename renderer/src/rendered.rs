@@ -0,0 +1,190 @@
+use crate::{Error, ErrorKind, EventInfo, SseFrame};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+/// An event rendered once and encoded by each transport as it needs
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedEvent {
+    /// the sequence id assigned when the event is published, see `EventHub`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub info: EventInfo,
+    /// the event data as serialized, its fields in the order they're declared
+    pub data: Box<RawValue>,
+    /// the templated HTML, `None` if the event has no template
+    pub html: Option<String>,
+}
+
+/// How a [`RenderedEvent`] gets encoded into a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventFormat {
+    /// `{"info": ..., "data": ...}`, then a newline and the HTML if any. It's the format of
+    /// `RenderEvent::render_event_data`, clients split it on the first newline.
+    #[default]
+    Lines,
+    /// a single JSON object: `{"info": ..., "data": ..., "html": ...}`
+    Envelope,
+}
+
+impl RenderedEvent {
+    pub fn encode(&self, format: EventFormat) -> Result<String, Error> {
+        match format {
+            EventFormat::Lines => {
+                let mut ret = crate::__private::event_json(&self.info, &self.data)?;
+                if let Some(html) = &self.html {
+                    ret.push('\n');
+                    ret.push_str(html);
+                }
+                Ok(ret)
+            }
+            EventFormat::Envelope => to_json(self.info.name, self),
+        }
+    }
+
+    /// the event as a `text/event-stream` frame with the data encoded in `format`. The id is set
+    /// as the frame id, for the browser to send it back as `Last-Event-ID` when reconnecting.
+    pub fn to_sse_frame(&self, format: EventFormat) -> Result<SseFrame, Error> {
+        let frame = SseFrame::new(self.info.name, self.encode(format)?);
+        Ok(match self.id {
+            Some(id) => frame.id(id.to_string()),
+            None => frame,
+        })
+    }
+
+    /// split the `json\nhtml` format of `RenderEvent::render_event_data`
+    pub(crate) fn from_lines(info: EventInfo, lines: &str) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Payload {
+            data: Option<Box<RawValue>>,
+        }
+
+        let (json, html) = match lines.split_once('\n') {
+            Some((json, html)) => (json, Some(html.to_string())),
+            None => (lines, None),
+        };
+        let payload: Payload = serde_json::from_str(json).map_err(|e| {
+            Error::new(
+                ErrorKind::BadSerialization,
+                format!("event {} has invalid JSON data", info.name),
+            )
+            .with_source(e)
+        })?;
+        let data = match payload.data {
+            Some(data) => data,
+            None => to_raw_value(info.name, &())?,
+        };
+        Ok(Self {
+            id: None,
+            info,
+            data,
+            html,
+        })
+    }
+}
+
+pub(crate) fn to_json<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<String, Error> {
+    serde_json::to_string(value).map_err(|e| serialization_error(name, e))
+}
+
+pub(crate) fn to_raw_value<T: Serialize + ?Sized>(
+    name: &str,
+    value: &T,
+) -> Result<Box<RawValue>, Error> {
+    serde_json::value::to_raw_value(value).map_err(|e| serialization_error(name, e))
+}
+
+fn serialization_error(name: &str, e: serde_json::Error) -> Error {
+    Error::new(
+        ErrorKind::BadSerialization,
+        format!("failed to serialize event {name}"),
+    )
+    .with_source(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn info() -> EventInfo {
+        fixtures::info("message", &["#list"])
+    }
+
+    fn raw(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    const INFO: &str = r##"{"name":"message","receivers":["#list"],"target":"#list","swap":"beforeend","idField":"id"}"##;
+
+    #[test]
+    fn rendered_event_should_encode_lines() {
+        let event = RenderedEvent {
+            id: None,
+            info: info(),
+            data: raw(r#"{"id":1}"#),
+            html: Some("<li>\nfoo</li>".to_string()),
+        };
+        assert_eq!(
+            event.encode(EventFormat::Lines).unwrap(),
+            format!("{{\"info\":{INFO},\"data\":{{\"id\":1}}}}\n<li>\nfoo</li>")
+        );
+
+        let event = RenderedEvent {
+            html: None,
+            ..event
+        };
+        assert_eq!(
+            event.encode(EventFormat::Lines).unwrap(),
+            format!("{{\"info\":{INFO},\"data\":{{\"id\":1}}}}")
+        );
+    }
+
+    #[test]
+    fn rendered_event_should_encode_envelope() {
+        let event = RenderedEvent {
+            id: None,
+            info: info(),
+            data: raw(r#"{"id":1}"#),
+            html: Some("<li>foo</li>".to_string()),
+        };
+        assert_eq!(
+            event.encode(EventFormat::Envelope).unwrap(),
+            format!("{{\"info\":{INFO},\"data\":{{\"id\":1}},\"html\":\"<li>foo</li>\"}}")
+        );
+        let frame = event.to_sse_frame(EventFormat::Envelope).unwrap();
+        assert_eq!(frame.event(), "message");
+        assert_eq!(frame.data(), event.encode(EventFormat::Envelope).unwrap());
+
+        let event = RenderedEvent {
+            id: Some(42),
+            ..event
+        };
+        assert_eq!(
+            event.encode(EventFormat::Envelope).unwrap(),
+            format!(
+                "{{\"id\":42,\"info\":{INFO},\"data\":{{\"id\":1}},\"html\":\"<li>foo</li>\"}}"
+            )
+        );
+        let frame = event.to_sse_frame(EventFormat::Lines).unwrap();
+        assert!(frame
+            .to_string()
+            .starts_with("event: message\nid: 42\ndata: "));
+    }
+
+    #[test]
+    fn rendered_event_should_round_trip_lines() {
+        let event = RenderedEvent {
+            id: None,
+            info: info(),
+            data: raw(r#"{"id":1,"tags":["a"]}"#),
+            html: Some("<li>\nfoo</li>".to_string()),
+        };
+        let lines = event.encode(EventFormat::Lines).unwrap();
+        let parsed = RenderedEvent::from_lines(info(), &lines).unwrap();
+        assert_eq!(parsed.data.get(), event.data.get());
+        assert_eq!(parsed.html, event.html);
+
+        let err = RenderedEvent::from_lines(info(), "not json\n<p></p>").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadSerialization);
+    }
+}
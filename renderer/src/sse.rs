@@ -0,0 +1,123 @@
+use std::{borrow::Cow, fmt, time::Duration};
+
+/// A `text/event-stream` frame. `Display` writes it in the wire format, ending with the blank
+/// line that dispatches the event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseFrame {
+    pub(crate) event: Cow<'static, str>,
+    pub(crate) data: String,
+    pub(crate) id: Option<String>,
+    pub(crate) retry: Option<Duration>,
+}
+
+impl SseFrame {
+    pub fn new(event: impl Into<Cow<'static, str>>, data: impl Into<String>) -> Self {
+        Self {
+            event: event.into(),
+            data: data.into(),
+            id: None,
+            retry: None,
+        }
+    }
+
+    /// set the `id:` field. Line breaks and NUL aren't allowed in it and get removed.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let mut id = id.into();
+        id.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+        self.id = Some(id);
+        self
+    }
+
+    /// set the `retry:` field, the reconnection time of the client
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+impl fmt::Display for SseFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = self.event.replace(['\r', '\n'], "");
+        writeln!(f, "event: {event}")?;
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {id}")?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        for line in lines(&self.data) {
+            writeln!(f, "data: {line}")?;
+        }
+        writeln!(f)
+    }
+}
+
+/// split on CRLF, CR or LF, the line endings of the event stream format
+pub(crate) fn lines(data: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(data);
+    std::iter::from_fn(move || {
+        let data = rest?;
+        match data.find(['\r', '\n']) {
+            Some(i) => {
+                let skip = if data[i..].starts_with("\r\n") { 2 } else { 1 };
+                rest = Some(&data[i + skip..]);
+                Some(&data[..i])
+            }
+            None => {
+                rest = None;
+                Some(data)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        __private,
+        fixtures::{info, TestEvent},
+        RenderEvent, Renderer,
+    };
+
+    #[test]
+    fn sse_frame_should_prefix_every_line() {
+        let event = TestEvent::new(info("message", &["#list"]), "alice");
+        let frame = event.to_sse_frame(&Renderer::default()).unwrap();
+        let json = __private::event_json(&event.info, "alice").unwrap();
+        assert_eq!(
+            frame.to_string(),
+            format!("event: message\ndata: {json}\ndata: <li>alice</li>\n\n")
+        );
+    }
+
+    #[test]
+    fn sse_frame_should_split_blank_lines_and_crlf() {
+        let frame = SseFrame::new("foo", "a\r\n\r\nb\rc\n\nd\n");
+        assert_eq!(
+            frame.to_string(),
+            "event: foo\ndata: a\ndata: \ndata: b\ndata: c\ndata: \ndata: d\ndata: \n\n"
+        );
+        let frame = SseFrame::new("foo", "");
+        assert_eq!(frame.to_string(), "event: foo\ndata: \n\n");
+    }
+
+    #[test]
+    fn sse_frame_should_write_id_and_retry() {
+        let frame = SseFrame::new("foo", "bar")
+            .id("4\r\n2")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            frame.to_string(),
+            "event: foo\nid: 42\nretry: 3000\ndata: bar\n\n"
+        );
+    }
+}
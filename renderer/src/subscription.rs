@@ -0,0 +1,56 @@
+use crate::EventInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The events a client subscribed to, by event name or receiver, e.g. `["message", "#room-42"]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Subscription(HashSet<String>);
+
+impl Subscription {
+    pub fn new<I, T>(topics: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self(topics.into_iter().map(Into::into).collect())
+    }
+
+    /// whether the event name or any of its receivers is subscribed. The name comes from the
+    /// event info, so an enum of events matches the name of the variant.
+    pub fn matches(&self, info: &EventInfo) -> bool {
+        self.0.contains(info.name)
+            || info
+                .receivers
+                .iter()
+                .any(|receiver| self.0.contains(receiver.as_ref()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: Into<String>> FromIterator<T> for Subscription {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::info;
+
+    #[test]
+    fn subscription_should_match_names_and_receivers() {
+        let subscription: Subscription =
+            serde_json::from_str(r##"["message", "#room-42"]"##).unwrap();
+        assert!(subscription.matches(&info("message", &["#inbox"])));
+        assert!(subscription.matches(&info("joined", &["#room-1", "#room-42"])));
+        assert!(!subscription.matches(&info("joined", &["#room-1"])));
+        // receivers are compared as selectors
+        assert!(!subscription.matches(&info("joined", &["#room-4"])));
+        assert!(!Subscription::default().matches(&info("message", &["#inbox"])));
+    }
+}
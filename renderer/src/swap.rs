@@ -0,0 +1,96 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// How htmx swaps the event HTML into the target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Swap {
+    #[default]
+    InnerHtml,
+    OuterHtml,
+    BeforeBegin,
+    AfterBegin,
+    BeforeEnd,
+    AfterEnd,
+    Delete,
+    None,
+}
+
+/// A swap style with its modifiers, e.g. `beforeend scroll:bottom settle:100ms`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwapSpec {
+    pub style: Swap,
+    /// space separated modifiers, checked by the `Event` derive
+    pub modifiers: &'static str,
+}
+
+impl Swap {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Swap::InnerHtml => "innerHTML",
+            Swap::OuterHtml => "outerHTML",
+            Swap::BeforeBegin => "beforebegin",
+            Swap::AfterBegin => "afterbegin",
+            Swap::BeforeEnd => "beforeend",
+            Swap::AfterEnd => "afterend",
+            Swap::Delete => "delete",
+            Swap::None => "none",
+        }
+    }
+}
+
+impl SwapSpec {
+    pub const fn new(style: Swap, modifiers: &'static str) -> Self {
+        Self { style, modifiers }
+    }
+}
+
+impl From<Swap> for SwapSpec {
+    fn from(style: Swap) -> Self {
+        Self::new(style, "")
+    }
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for SwapSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.style.as_str())?;
+        if !self.modifiers.is_empty() {
+            write!(f, " {}", self.modifiers)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Swap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Serialize for SwapSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_should_serialize_to_htmx_strings() {
+        let spec = SwapSpec::new(Swap::BeforeEnd, "scroll:bottom settle:100ms");
+        assert_eq!(
+            serde_json::to_string(&spec).unwrap(),
+            r#""beforeend scroll:bottom settle:100ms""#
+        );
+        let spec = SwapSpec::from(Swap::InnerHtml);
+        assert_eq!(serde_json::to_string(&spec).unwrap(), r#""innerHTML""#);
+        assert_eq!(serde_json::to_string(&Swap::None).unwrap(), r#""none""#);
+    }
+}
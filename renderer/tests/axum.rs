@@ -0,0 +1,328 @@
+#![cfg(feature = "with-axum")]
+
+use axum_core::{body::Body, response::Response};
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use http_body_util::BodyExt;
+use jinja_renderer::{embed_templates, RenderContext, Renderer, Template};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[cfg(any(feature = "with-axum-sse", feature = "with-axum-ws"))]
+use jinja_renderer::{Event, RenderEvent};
+#[cfg(feature = "minify")]
+use jinja_renderer::{MinifyConfig, OwnedTemplate};
+
+#[derive(Debug, Serialize, Template)]
+#[template(name = "pages/layout.html.j2", partial_block = "content")]
+struct Layout<'a> {
+    title: &'a str,
+}
+
+#[derive(Debug, Serialize, Template)]
+#[template(
+    name = "pages/layout.html.j2",
+    partial_block = "content",
+    partial_target = "main"
+)]
+struct TargetedLayout<'a> {
+    title: &'a str,
+}
+
+#[derive(Debug, Serialize, Template)]
+#[template(
+    source = "{% for row in rows %}row {{ row }}\n{% endfor %}",
+    mime = "text/plain; charset=utf-8"
+)]
+struct Report {
+    rows: Vec<usize>,
+}
+
+#[cfg(feature = "minify")]
+#[derive(Debug, Serialize, Template)]
+#[template(name = "rows.html.j2")]
+struct Rows {
+    rows: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Template)]
+#[template(name = "missing.html.j2")]
+struct Missing {}
+
+/// joins `rooms`, rendering fails without a user
+#[cfg(any(feature = "with-axum-sse", feature = "with-axum-ws"))]
+#[derive(Debug, Serialize, Template, Event)]
+#[template(source = "{% if user %}<li>{{ user }}</li>{% else %}{{ user.fail() }}{% endif %}")]
+#[event(
+    name = "joined",
+    receivers_field = "rooms",
+    receivers_prefix = "#room-",
+    target = "#members",
+    swap = "beforeend"
+)]
+struct Joined {
+    rooms: Vec<u32>,
+    user: &'static str,
+}
+
+#[cfg(any(feature = "with-axum-sse", feature = "with-axum-ws"))]
+impl Joined {
+    fn new(room: u32, user: &'static str) -> Self {
+        Self {
+            rooms: vec![room],
+            user,
+        }
+    }
+}
+
+#[tokio::test]
+async fn htmx_request_should_render_partial_block() {
+    let renderer = get_renderer();
+    let data = Layout { title: "hello" };
+
+    let res = renderer.render_htmx_response(&htmx_headers(None), &data);
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::VARY], "HX-Request");
+    assert_eq!(
+        res.headers()[header::CONTENT_TYPE],
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(body(res).await, "<main>hello</main>");
+}
+
+#[tokio::test]
+async fn regular_request_should_render_full_page() {
+    let renderer = get_renderer();
+    let data = Layout { title: "hello" };
+
+    let res = renderer.render_htmx_response(&HeaderMap::new(), &data);
+    assert_eq!(res.headers()[header::VARY], "HX-Request");
+    assert!(body(res).await.contains("<title>hello</title>"));
+
+    // history restore needs the full page as well
+    let mut headers = htmx_headers(None);
+    headers.insert(
+        "HX-History-Restore-Request",
+        HeaderValue::from_static("true"),
+    );
+    let res = renderer.render_htmx_response(&headers, &data);
+    assert!(body(res).await.contains("<title>hello</title>"));
+}
+
+#[tokio::test]
+async fn htmx_request_should_respect_partial_target() {
+    let renderer = get_renderer();
+    let data = TargetedLayout { title: "hello" };
+
+    let res = renderer.render_htmx_response(&htmx_headers(Some("main")), &data);
+    assert_eq!(body(res).await, "<main>hello</main>");
+
+    let res = renderer.render_htmx_response(&htmx_headers(Some("sidebar")), &data);
+    assert!(body(res).await.contains("<title>hello</title>"));
+}
+
+#[tokio::test]
+async fn stream_response_should_send_chunks() {
+    let renderer = Arc::new(get_renderer());
+    let data = Report {
+        rows: (0..10_000).collect(),
+    };
+    let expected = data.render(&renderer).unwrap();
+
+    let res = renderer.render_stream_response(data).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers()[header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    let chunks = chunks(res).await;
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), expected.as_bytes());
+}
+
+#[cfg(feature = "minify")]
+#[tokio::test]
+async fn stream_response_should_stream_html_minified_on_load() {
+    let mut renderer =
+        Renderer::default().with_minify_config(MinifyConfig::default().minify_on_load(true));
+    let source = "<ul>\n  {% for row in rows %}\n    <li>{{ row }}</li>\n  {% endfor %}\n</ul>\n";
+    renderer
+        .add_templates([OwnedTemplate::new("rows.html.j2", source)].into_iter())
+        .unwrap();
+    let renderer = Arc::new(renderer);
+    let data = Rows {
+        rows: (0..10_000).collect(),
+    };
+    let expected = data.render(&renderer).unwrap();
+    assert!(expected.starts_with("<ul> <li>0</li> <li>1</li>"));
+
+    let res = renderer.render_stream_response(data).await;
+    let chunks = chunks(res).await;
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), expected.as_bytes());
+}
+
+#[tokio::test]
+async fn stream_response_should_fail_before_sending_anything() {
+    let renderer = Arc::new(get_renderer());
+    let res = renderer.render_stream_response(Missing {}).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[cfg(feature = "with-axum-sse")]
+#[tokio::test]
+async fn sse_should_render_events_and_skip_failures() {
+    use axum_core::response::IntoResponse;
+
+    let renderer = Arc::new(get_renderer());
+    let events = futures_util::stream::iter([
+        Joined::new(1, "alice"),
+        Joined::new(1, ""),
+        Joined::new(2, "bob"),
+    ]);
+    let expected = [Joined::new(1, "alice"), Joined::new(2, "bob")].map(|e| {
+        let data = e
+            .render_event_data(&renderer)
+            .unwrap()
+            .replace('\n', "\ndata: ");
+        format!("event: joined\ndata: {data}\n\n")
+    });
+    let res = renderer.render_sse(events).into_response();
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "text/event-stream");
+    assert_eq!(body(res).await, expected.concat());
+}
+
+#[cfg(all(feature = "hub", feature = "with-axum-sse"))]
+#[tokio::test]
+async fn sse_should_replay_the_events_missed_after_last_event_id() {
+    use axum_core::response::IntoResponse;
+    use jinja_renderer::{EventFormat, EventHub, Subscription};
+
+    let renderer = Arc::new(get_renderer());
+    let hub = EventHub::new(renderer.clone(), 8).with_history(8);
+    let frame = |id: u64, user| {
+        let data = Joined::new(1, user)
+            .render_event_data(&renderer)
+            .unwrap()
+            .replace('\n', "\ndata: ");
+        format!("event: joined\ndata: {data}\nid: {id}\n\n")
+    };
+
+    let res = hub
+        .subscribe_sse(
+            &HeaderMap::new(),
+            Subscription::new(["#room-1"]),
+            EventFormat::Lines,
+        )
+        .into_response();
+    hub.publish(&Joined::new(1, "alice")).unwrap();
+    hub.publish(&Joined::new(2, "bob")).unwrap();
+    hub.publish(&Joined::new(1, "carol")).unwrap();
+    let mut body = res.into_body();
+    assert_eq!(next_event(&mut body).await, frame(1, "alice"));
+    assert_eq!(next_event(&mut body).await, frame(3, "carol"));
+    drop(body);
+
+    // the browser reconnects with the id of the last event it got
+    let mut headers = HeaderMap::new();
+    headers.insert("Last-Event-ID", HeaderValue::from_static("1"));
+    let res = hub
+        .subscribe_sse(&headers, Subscription::new(["#room-1"]), EventFormat::Lines)
+        .into_response();
+    hub.publish(&Joined::new(1, "dave")).unwrap();
+    let mut body = res.into_body();
+    assert_eq!(next_event(&mut body).await, frame(3, "carol"));
+    assert_eq!(next_event(&mut body).await, frame(4, "dave"));
+}
+
+#[cfg(feature = "with-axum-ws")]
+#[tokio::test]
+async fn ws_should_send_subscribed_events() {
+    use axum::{extract::ws::WebSocketUpgrade, routing::get, Router};
+    use futures_util::StreamExt;
+    use jinja_renderer::{EventFormat, Subscription};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let app = Router::new().route(
+        "/ws",
+        get(|ws: WebSocketUpgrade| async {
+            ws.on_upgrade(|socket| async move {
+                let renderer = get_renderer();
+                let events = futures_util::stream::iter([
+                    Joined::new(1, "alice"),
+                    Joined::new(2, "bob"),
+                    Joined::new(1, "carol"),
+                ]);
+                let subscription = Subscription::new(["#room-1"]);
+                renderer
+                    .render_ws(socket, events, &subscription, EventFormat::Envelope)
+                    .await
+                    .unwrap();
+            })
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .unwrap();
+    let mut frames = Vec::new();
+    while let Some(Ok(Message::Text(text))) = ws.next().await {
+        frames.push(serde_json::from_str::<serde_json::Value>(&text).unwrap());
+    }
+    let users = frames
+        .iter()
+        .map(|f| (f["data"]["user"].as_str(), f["html"].as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        users,
+        [
+            (Some("alice"), Some("<li>alice</li>")),
+            (Some("carol"), Some("<li>carol</li>"))
+        ]
+    );
+    assert_eq!(frames[0]["info"]["name"], "joined");
+}
+
+fn get_renderer() -> Renderer {
+    let mut renderer = Renderer::default();
+    renderer
+        .add_templates(embed_templates!("tests/templates"))
+        .unwrap();
+    renderer.add_inline_template::<Report>().unwrap();
+    #[cfg(any(feature = "with-axum-sse", feature = "with-axum-ws"))]
+    renderer.add_inline_template::<Joined>().unwrap();
+    renderer
+}
+
+fn htmx_headers(target: Option<&'static str>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("HX-Request", HeaderValue::from_static("true"));
+    if let Some(target) = target {
+        headers.insert("HX-Target", HeaderValue::from_static(target));
+    }
+    headers
+}
+
+async fn chunks(res: Response) -> Vec<bytes::Bytes> {
+    let mut body = res.into_body();
+    let mut chunks = Vec::new();
+    while let Some(frame) = body.frame().await {
+        chunks.push(frame.unwrap().into_data().unwrap());
+    }
+    chunks
+}
+
+async fn body(res: Response) -> String {
+    let body: Body = res.into_body();
+    let bytes = body.collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// the next event of an endless SSE body
+#[cfg(all(feature = "hub", feature = "with-axum-sse"))]
+async fn next_event(body: &mut Body) -> String {
+    let frame = body.frame().await.unwrap().unwrap();
+    String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap()
+}
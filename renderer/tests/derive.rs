@@ -1,4 +1,8 @@
-use jinja_renderer::{OwnedTemplate, RenderContext, Renderer, Template};
+use jinja_renderer::{
+    embed_templates, Event, EventFormat, OwnedTemplate, RenderContext, RenderEvent, Renderer,
+    Template,
+};
+use minijinja::Environment;
 use once_cell::sync::OnceCell;
 use serde::Serialize;
 
@@ -10,6 +14,62 @@ struct Foo<'a> {
     bar: &'a str,
 }
 
+#[derive(Debug, Serialize, Template)]
+#[template(name = "pages/home.html.j2", root = "tests/templates")]
+struct Home<'a> {
+    title: &'a str,
+}
+
+#[derive(Debug, Serialize, Template)]
+#[template(name = "pages/layout.html.j2", root = "tests/templates")]
+struct Layout<'a> {
+    title: &'a str,
+}
+
+#[derive(Debug, Serialize, Template)]
+#[template(source = "<li>{{ name }}</li>")]
+struct Item<'a> {
+    name: &'a str,
+}
+
+#[derive(Debug, Serialize, Template, Event)]
+#[template(source = "<li>{{ name }}</li>")]
+#[event(
+    name = "item_added",
+    receivers = "#list",
+    target = "#list",
+    swap = "beforeend"
+)]
+struct ItemAdded<'a> {
+    name: &'a str,
+}
+
+#[derive(Debug, Serialize, Template, Event)]
+#[template(source = "<li>{{ player }}: {{ score }}</li>")]
+#[event(
+    name = "scored",
+    receivers = "#scores",
+    target = "#scores",
+    swap = "beforeend"
+)]
+struct Scored<'a> {
+    score: u32,
+    player: &'a str,
+    bonus: bool,
+}
+
+#[derive(Debug, Serialize, Event)]
+#[event(
+    name = "message",
+    receivers = "#inbox",
+    receivers_field = "room_ids",
+    receivers_prefix = "#room-",
+    target = "#messages"
+)]
+struct Message {
+    room_ids: Vec<u64>,
+}
+
 #[cfg(feature = "minify")]
 #[test]
 fn test_minify() {
@@ -30,6 +90,153 @@ fn test_not_minify() {
     assert_eq!(ret, "<html>\n  <body>\n    baz\n  </body>\n</html>");
 }
 
+#[test]
+fn test_embed_templates() {
+    let mut renderer = Renderer::default();
+    renderer
+        .add_templates(embed_templates!("tests/templates"))
+        .unwrap();
+
+    let ret = Home { title: "hello" }.render(&renderer).unwrap();
+    assert_eq!(ret, "<main>hello</main>");
+    assert!(renderer.get_template("foo.html.j2").is_ok());
+}
+
+#[test]
+fn test_inline_template() {
+    let mut renderer = Renderer::default();
+    renderer.add_inline_template::<Item>().unwrap();
+    renderer
+        .add_template(
+            "list.html.j2",
+            "<ul>{% include \"derive::Item.html.j2\" %}</ul>",
+        )
+        .unwrap();
+
+    let ret = Item { name: "foo" }.render(&renderer).unwrap();
+    assert_eq!(ret, "<li>foo</li>");
+    assert_eq!(Item::TEMPLATE_NAME, "derive::Item.html.j2");
+    let ret = renderer
+        .render_template("list.html.j2", &Item { name: "bar" })
+        .unwrap();
+    assert_eq!(ret, "<ul><li>bar</li></ul>");
+}
+
+#[test]
+fn test_inline_template_with_loader() {
+    let mut env = Environment::new();
+    env.set_loader(|name| Ok((name == "loaded.j2").then(|| "loaded {{ name }}".into())));
+    let mut renderer = Renderer::new(env);
+    renderer.add_inline_template::<Item>().unwrap();
+
+    let ret = renderer
+        .render_template("loaded.j2", &Item { name: "foo" })
+        .unwrap();
+    assert_eq!(ret, "loaded foo");
+    let ret = Item { name: "bar" }.render(&renderer).unwrap();
+    assert_eq!(ret, "<li>bar</li>");
+}
+
+#[test]
+fn test_render_block() {
+    let mut renderer = Renderer::default();
+    renderer
+        .add_templates(embed_templates!("tests/templates"))
+        .unwrap();
+
+    let data = Layout { title: "hello" };
+    let ret = data.render_block(&renderer, "content").unwrap();
+    assert_eq!(ret, "<main>hello</main>");
+    assert!(data
+        .render(&renderer)
+        .unwrap()
+        .contains("<title>hello</title>"));
+
+    let err = data.render_block(&renderer, "missing").unwrap_err();
+    assert_eq!(err.kind(), jinja_renderer::ErrorKind::UnknownBlock);
+}
+
+#[test]
+fn test_render_to_writer() {
+    let renderer = renderer_with::<Item>();
+    let mut buf = Vec::new();
+    Item { name: "foo" }
+        .render_to_writer(&renderer, &mut buf)
+        .unwrap();
+    assert_eq!(buf, b"<li>foo</li>");
+
+    let err = Foo { bar: "baz" }
+        .render_to_writer(&renderer, &mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), jinja_renderer::ErrorKind::TemplateNotFound);
+}
+
+#[test]
+fn test_render_oob() {
+    let renderer = renderer_with::<ItemAdded>();
+    let ret = ItemAdded { name: "foo" }.render_oob(&renderer).unwrap();
+    assert_eq!(
+        ret.unwrap(),
+        r#"<template hx-swap-oob="beforeend:#list"><li>foo</li></template>"#
+    );
+}
+
+#[test]
+fn test_render_event() {
+    let renderer = renderer_with::<ItemAdded>();
+    let event = ItemAdded { name: "foo" }.render_event(&renderer).unwrap();
+    assert_eq!(event.info.name, "item_added");
+    assert_eq!(event.data.get(), r#"{"name":"foo"}"#);
+    assert_eq!(event.html.as_deref(), Some("<li>foo</li>"));
+    assert_eq!(
+        event.encode(EventFormat::Lines).unwrap(),
+        ItemAdded { name: "foo" }
+            .render_event_data(&renderer)
+            .unwrap()
+    );
+
+    let event = Message { room_ids: vec![1] }
+        .render_event(&renderer)
+        .unwrap();
+    assert_eq!(event.html, None);
+    let envelope: serde_json::Value =
+        serde_json::from_str(&event.encode(EventFormat::Envelope).unwrap()).unwrap();
+    assert_eq!(envelope["data"], serde_json::json!({ "room_ids": [1] }));
+    assert_eq!(
+        envelope["info"]["receivers"],
+        serde_json::json!(["#inbox", "#room-1"])
+    );
+    assert!(envelope["html"].is_null());
+}
+
+#[test]
+fn test_render_event_keeps_field_order() {
+    let renderer = renderer_with::<Scored>();
+    let event = Scored {
+        score: 3,
+        player: "foo",
+        bonus: true,
+    };
+    let rendered = event.render_event(&renderer).unwrap();
+    assert_eq!(
+        rendered.data.get(),
+        r#"{"score":3,"player":"foo","bonus":true}"#
+    );
+    assert_eq!(
+        rendered.encode(EventFormat::Lines).unwrap(),
+        event.render_event_data(&renderer).unwrap()
+    );
+}
+
+#[test]
+fn test_dynamic_receivers() {
+    let info = Message {
+        room_ids: vec![1, 42],
+    }
+    .event_info();
+    assert_eq!(info.receivers, ["#inbox", "#room-1", "#room-42"]);
+}
+
 fn get_render() -> &'static Renderer {
     RENDERER.get_or_init(|| {
         let templates = vec![OwnedTemplate::new(
@@ -43,3 +250,9 @@ fn get_render() -> &'static Renderer {
         renderer
     })
 }
+
+fn renderer_with<T: RenderContext>() -> Renderer {
+    let mut renderer = Renderer::default();
+    renderer.add_inline_template::<T>().unwrap();
+    renderer
+}
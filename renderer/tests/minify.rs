@@ -0,0 +1,201 @@
+#![cfg(feature = "minify")]
+
+use jinja_renderer::{MinifyConfig, OwnedTemplate, Renderer};
+use minijinja::context;
+
+const LAYOUT: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>{% block title %}{{ title }}{% endblock %}</title>
+    <!-- shared styles -->
+    <link rel="stylesheet" href="/app.css">
+  </head>
+  <body class="{{ theme }}">
+    {% block content %}{% endblock %}
+  </body>
+</html>
+"#;
+
+const PAGE: &str = r#"{% extends "layout.html.j2" %}
+{% block content %}
+  <main id="main">
+    <h1>  {{ title | upper }}  </h1>
+    {# a jinja comment #}
+    <ul>
+      {% for item in items %}
+        <li class="{% if loop.first %}first{% endif %}" data-id="{{ item.id }}">
+          {{ item.name }}
+        </li>
+      {% else %}
+        <li>nothing here</li>
+      {% endfor %}
+    </ul>
+    {% include "footer.html.j2" %}
+  </main>
+{% endblock %}
+"#;
+
+const FOOTER: &str = r#"<footer>
+  {% if user %}<a href="/users/{{ user }}">{{ user }}</a>{% else %}<a href="/login">login</a>{% endif %}
+</footer>
+"#;
+
+const FORM: &str = r#"{% macro input(name, value="") %}
+  <input type="text" name="{{ name }}" value="{{ value }}">
+{% endmacro %}
+<form method="post">
+  {{ input("email") }}
+  {{ input("name", value=title) }}
+  <button   type="submit">  Save </button>
+</form>
+"#;
+
+const WIDGET: &str = r#"<div id="widget">
+  <style>p { color: {{ theme }}; }  a { color: red; }</style>
+  <script>var title = "{{ title }}";  var y = 2;</script>
+</div>
+"#;
+
+const DATA: &str = r#"{"title": "{{ title }}"}
+"#;
+
+#[test]
+fn minify_on_load_should_match_per_render_minify() {
+    let contexts = [
+        context!(title => "hello", theme => "dark", user => "tyr", items => vec![
+            context!(id => 1, name => "first"),
+            context!(id => 2, name => "second"),
+        ]),
+        context!(title => "empty", theme => "light", items => Vec::<()>::new()),
+    ];
+
+    let names = [
+        "layout.html.j2",
+        "page.html.j2",
+        "footer.html.j2",
+        "form.html.j2",
+        "widget.html.j2",
+        "data.json.j2",
+    ];
+    let configs = [
+        MinifyConfig::default(),
+        MinifyConfig::default().minify_js(true).minify_css(true),
+    ];
+    for config in configs {
+        let per_render = renderer(config.clone());
+        let on_load = renderer(config.clone().minify_on_load(true));
+        let unminified = renderer(config.exclude("*").unwrap());
+        for name in names {
+            for ctx in &contexts {
+                let expected = per_render.render_template(name, ctx).unwrap();
+                let actual = on_load.render_template(name, ctx).unwrap();
+                let raw = unminified.render_template(name, ctx).unwrap();
+                // the source can't be minified as far as the output, e.g. templated attributes
+                // keep their quotes, but what's left is only what minifying would remove
+                assert!(actual.len() <= raw.len(), "{name}");
+                assert_eq!(minify(&per_render, name, &actual), expected, "{name}");
+            }
+        }
+    }
+}
+
+#[test]
+fn templated_scripts_and_styles_should_be_minified_on_render() {
+    let config = MinifyConfig::default().minify_js(true).minify_css(true);
+    let renderer = renderer(config.minify_on_load(true));
+    let ret = renderer
+        .render_template(
+            "widget.html.j2",
+            &context!(title => "${x}", theme => "dark"),
+        )
+        .unwrap();
+    assert_eq!(
+        ret,
+        r#"<div id=widget><style>p{color:dark}a{color:red}</style><script>var title=`\${x}`;var y=2</script></div>"#
+    );
+    let tpl = renderer.get_template("widget.html.j2").unwrap();
+    assert!(tpl.source().contains('\n'));
+
+    // without JS or CSS minification it's minified on load
+    let renderer = self::renderer(MinifyConfig::default().minify_on_load(true));
+    let tpl = renderer.get_template("widget.html.j2").unwrap();
+    assert!(!tpl.source().contains('\n'));
+}
+
+#[test]
+fn minify_on_load_should_respect_exclude() {
+    let config = MinifyConfig::default()
+        .minify_on_load(true)
+        .exclude("form.*")
+        .unwrap();
+    let renderer = renderer(config);
+    let ret = renderer
+        .render_template("form.html.j2", &context!(title => "x"))
+        .unwrap();
+    assert!(ret.contains("<button   type=\"submit\">  Save </button>"));
+}
+
+#[test]
+fn minify_on_load_should_minify_the_source() {
+    let renderer = renderer(MinifyConfig::default().minify_on_load(true));
+    let tpl = renderer.get_template("footer.html.j2").unwrap();
+    assert!(!tpl.source().contains('\n'));
+}
+
+#[test]
+fn templates_added_through_the_env_should_be_minified_on_render() {
+    let mut renderer = renderer(MinifyConfig::default().minify_on_load(true));
+    renderer
+        .add_template("footer.html.j2", "<p>  a  </p>\n\n<p> b </p>")
+        .unwrap();
+    let ret = renderer
+        .render_template("footer.html.j2", &context!())
+        .unwrap();
+    assert_eq!(ret, "<p>a</p><p>b</p>");
+}
+
+#[test]
+fn set_minify_config_should_minify_loaded_templates_again() {
+    let ctx = context!(title => "x");
+    let mut renderer = renderer(MinifyConfig::default().minify_on_load(true));
+
+    renderer.set_minify_config(MinifyConfig::default().exclude("form.*").unwrap());
+    let ret = renderer.render_template("form.html.j2", &ctx).unwrap();
+    assert!(ret.contains("<button   type=\"submit\">  Save </button>"));
+
+    renderer.set_minify_config(MinifyConfig::default().minify_on_load(true));
+    let ret = renderer.render_template("form.html.j2", &ctx).unwrap();
+    assert!(ret.contains("<button>Save</button>"));
+    let tpl = renderer.get_template("form.html.j2").unwrap();
+    assert!(!tpl.source().contains('\n'));
+}
+
+fn renderer(config: MinifyConfig) -> Renderer {
+    let templates = [
+        ("layout.html.j2", LAYOUT),
+        ("page.html.j2", PAGE),
+        ("footer.html.j2", FOOTER),
+        ("form.html.j2", FORM),
+        ("widget.html.j2", WIDGET),
+        ("data.json.j2", DATA),
+    ];
+    let mut renderer = Renderer::default().with_minify_config(config);
+    renderer
+        .add_templates(
+            templates
+                .into_iter()
+                .map(|(name, data)| OwnedTemplate::new(name, data)),
+        )
+        .unwrap();
+    renderer
+}
+
+/// minify `html` the way rendering `name` would
+fn minify(renderer: &Renderer, name: &str, html: &str) -> String {
+    let mut renderer = renderer.clone();
+    let source = format!("{{% raw %}}{html}{{% endraw %}}");
+    renderer
+        .add_template_owned(name.to_string(), source)
+        .unwrap();
+    renderer.render_template(name, &context!()).unwrap()
+}